@@ -1,27 +1,281 @@
-use std::{cell::{Cell, RefCell}, fs::File, io::Read, path::PathBuf};
+use std::{cell::{Cell, RefCell}, collections::HashMap, fs::File, io::Read, path::PathBuf, rc::Rc};
 
 #[cfg(not(test))] 
-use log::{info, warn}; // Use log crate when building application
+use log::info; // Use log crate when building application
  
 #[cfg(test)]
-use std::{println as info, println as warn}; // Workaround to use prinltn! for logs.
+use std::println as info; // Workaround to use prinltn! for logs.
 use thiserror::Error;
 
-use crate::{context::Context, directive::{DirectiveEnum, DirectiveType, DirectiveValue, SYSTEM_DIRECTIVES}, opcode::{BRANCH_INSTS, INSTS_SIZE}, parser::{Parser, Token, TokenType}, tool::print_error};
+use crate::{code_gen::CodeGeneratorError, context::Context, directive::{find_directive, DirectiveEnum, DirectiveType, DirectiveValue, SYSTEM_DIRECTIVES}, opcode::{find_instr, has_implied, has_mode, has_two_operands, is_available, is_branch, CpuType, ModeType, INSTS}, parser::{MacroCall, Operator, ParseError, Parser, Token, TokenInfo, TokenType}, tool::{line_text, print_code_error, print_macro_calls}};
+
+/* Maximum nested macro invocations, it also stops infinite recursion */
+pub const MAX_MACRO_DEPTH: usize = 64;
+
+/* Iteration variable is a word */
+pub const MAX_REPEAT_COUNT: usize = 0x10000;
+
+/* Nested repeats multiply, all '.rept' blocks together can not produce more tokens */
+pub const MAX_REPEAT_TOKENS: usize = 0x100000;
 
 #[derive(Debug, PartialEq)]
 pub enum InstrValue {
     Byte(u8),
     Word(u16),
+    Long(u32),
     Reference(String),
-    LocalReference(String)
+    LocalReference(String),
+    Expression(Expr)
 }
 
-#[derive(Debug, PartialEq)]
+impl InstrValue {
+    pub fn as_expr(&self) -> Expr {
+        match self {
+            InstrValue::Byte(byte) => Expr::Byte(*byte),
+            InstrValue::Word(word) => Expr::Word(*word),
+            InstrValue::Long(long) => Expr::Long(*long),
+            InstrValue::Reference(name) => Expr::Reference(name.clone()),
+            InstrValue::LocalReference(name) => Expr::LocalReference(name.clone()),
+            InstrValue::Expression(expr) => expr.clone()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+    LowByte,
+    HighByte,
+    BankByte,
+    LogicalNot
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+    LessThan,
+    GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    LogicalAnd,
+    LogicalOr
+}
+
+impl BinaryOperator {
+    pub fn from_operator(operator: Operator) -> Option<Self> {
+        match operator {
+            Operator::Plus => Some(BinaryOperator::Add),
+            Operator::Minus => Some(BinaryOperator::Subtract),
+            Operator::Multiply => Some(BinaryOperator::Multiply),
+            Operator::Divide => Some(BinaryOperator::Divide),
+            Operator::Modulo => Some(BinaryOperator::Modulo),
+            Operator::And => Some(BinaryOperator::And),
+            Operator::Or => Some(BinaryOperator::Or),
+            Operator::Xor => Some(BinaryOperator::Xor),
+            Operator::ShiftLeft => Some(BinaryOperator::ShiftLeft),
+            Operator::ShiftRight => Some(BinaryOperator::ShiftRight),
+            Operator::LessThan => Some(BinaryOperator::LessThan),
+            Operator::GreaterThan => Some(BinaryOperator::GreaterThan),
+            Operator::LessEqual => Some(BinaryOperator::LessEqual),
+            Operator::GreaterEqual => Some(BinaryOperator::GreaterEqual),
+            Operator::Equal => Some(BinaryOperator::Equal),
+            Operator::NotEqual => Some(BinaryOperator::NotEqual),
+            Operator::LogicalAnd => Some(BinaryOperator::LogicalAnd),
+            Operator::LogicalOr => Some(BinaryOperator::LogicalOr),
+            Operator::Not | Operator::LogicalNot => None
+        }
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, BinaryOperator::LessThan | BinaryOperator::GreaterThan | BinaryOperator::LessEqual | BinaryOperator::GreaterEqual | BinaryOperator::Equal | BinaryOperator::NotEqual | BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr)
+    }
+
+    /* Higher value binds stronger, same ordering with C */
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 10,
+            BinaryOperator::Add | BinaryOperator::Subtract => 9,
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 8,
+            BinaryOperator::LessThan | BinaryOperator::GreaterThan | BinaryOperator::LessEqual | BinaryOperator::GreaterEqual => 7,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 6,
+            BinaryOperator::And => 5,
+            BinaryOperator::Xor => 4,
+            BinaryOperator::Or => 3,
+            BinaryOperator::LogicalAnd => 2,
+            BinaryOperator::LogicalOr => 1
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Byte(u8),
+    Word(u16),
+    Long(u32),
+    Reference(String),
+    LocalReference(String),
+    ProgramCounter,
+    Unary(UnaryOperator, Box<Expr>),
+    Binary(BinaryOperator, Box<Expr>, Box<Expr>)
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ExprValue {
+    pub value: i64,
+    pub is_word: bool,
+    pub is_long: bool,
+    pub resolved: bool
+}
+
+impl ExprValue {
+    pub fn byte(value: u8) -> Self {
+        Self { value: value as i64, is_word: false, is_long: false, resolved: true }
+    }
+
+    pub fn word(value: u16) -> Self {
+        Self { value: value as i64, is_word: true, is_long: false, resolved: true }
+    }
+
+    pub fn long(value: u32) -> Self {
+        Self { value: value as i64, is_word: true, is_long: true, resolved: true }
+    }
+
+    /* Addresses above 16 bit are long (65816) */
+    pub fn address(value: usize) -> Self {
+        match value > 0xffff {
+            true => Self::long(value as u32),
+            false => Self::word(value as u16)
+        }
+    }
+
+    pub fn unresolved() -> Self {
+        Self { value: 0, is_word: true, is_long: false, resolved: false }
+    }
+
+    pub fn fits_byte(&self) -> bool {
+        (-128..=255).contains(&self.value)
+    }
+
+    pub fn fits_word(&self) -> bool {
+        (-32768..=65535).contains(&self.value)
+    }
+}
+
+impl Expr {
+    /* Symbols (references) are resolved by the caller, unresolved symbols are evaluated as zero */
+    pub fn evaluate<F>(&self, resolve: &mut F) -> Result<ExprValue, CodeGeneratorError> where F: FnMut(&Expr) -> Result<ExprValue, CodeGeneratorError> {
+        match self {
+            Expr::Byte(byte) => Ok(ExprValue::byte(*byte)),
+            Expr::Word(word) => Ok(ExprValue::word(*word)),
+            Expr::Long(long) => Ok(ExprValue::long(*long)),
+            Expr::Reference(_) | Expr::LocalReference(_) | Expr::ProgramCounter => resolve(self),
+            Expr::Unary(operator, expr) => {
+                let mut result = expr.evaluate(resolve)?;
+                let mask = match (result.is_long, result.is_word) {
+                    (true, _) => 0xff_ffff,
+                    (false, true) => 0xffff,
+                    (false, false) => 0xff
+                };
+
+                result.value = match operator {
+                    UnaryOperator::Negate => -result.value,
+                    UnaryOperator::Not => !result.value & mask,
+                    UnaryOperator::LowByte => result.value & 0xff,
+                    UnaryOperator::HighByte => (result.value >> 8) & 0xff,
+                    UnaryOperator::BankByte => (result.value >> 16) & 0xff,
+                    UnaryOperator::LogicalNot => (result.value == 0) as i64
+                };
+
+                if let UnaryOperator::LowByte | UnaryOperator::HighByte | UnaryOperator::BankByte | UnaryOperator::LogicalNot = operator {
+                    result.is_word = false;
+                    result.is_long = false;
+                }
+                Ok(result)
+            },
+            Expr::Binary(operator, left, right) => {
+                let left = left.evaluate(resolve)?;
+                let right = right.evaluate(resolve)?;
+                let resolved = left.resolved && right.resolved;
+
+                let value = match operator {
+                    BinaryOperator::Add => left.value.wrapping_add(right.value),
+                    BinaryOperator::Subtract => left.value.wrapping_sub(right.value),
+                    BinaryOperator::Multiply => left.value.wrapping_mul(right.value),
+                    BinaryOperator::Divide | BinaryOperator::Modulo if right.value == 0 => match resolved {
+                        true => return Err(CodeGeneratorError::DivisionByZero),
+                        false => 0
+                    },
+                    BinaryOperator::Divide => left.value.wrapping_div(right.value),
+                    BinaryOperator::Modulo => left.value.wrapping_rem(right.value),
+                    BinaryOperator::And => left.value & right.value,
+                    BinaryOperator::Or => left.value | right.value,
+                    BinaryOperator::Xor => left.value ^ right.value,
+                    BinaryOperator::ShiftLeft => left.value.checked_shl(right.value as u32).unwrap_or(0),
+                    BinaryOperator::ShiftRight => left.value.checked_shr(right.value as u32).unwrap_or(0),
+                    BinaryOperator::LessThan => (left.value < right.value) as i64,
+                    BinaryOperator::GreaterThan => (left.value > right.value) as i64,
+                    BinaryOperator::LessEqual => (left.value <= right.value) as i64,
+                    BinaryOperator::GreaterEqual => (left.value >= right.value) as i64,
+                    BinaryOperator::Equal => (left.value == right.value) as i64,
+                    BinaryOperator::NotEqual => (left.value != right.value) as i64,
+                    BinaryOperator::LogicalAnd => (left.value != 0 && right.value != 0) as i64,
+                    BinaryOperator::LogicalOr => (left.value != 0 || right.value != 0) as i64
+                };
+
+                /* Comparison results are always byte */
+                let is_word = !operator.is_comparison() && (left.is_word || right.is_word);
+                let is_long = !operator.is_comparison() && (left.is_long || right.is_long);
+                let mut result = ExprValue { value, is_word, is_long, resolved };
+                result.is_long |= !result.fits_word();
+                result.is_word |= result.is_long || !result.fits_byte();
+                Ok(result)
+            }
+        }
+    }
+
+    /* Replace program counter with a reference, returns true if there is a replacement */
+    pub fn replace_program_counter(&mut self, name: &str) -> bool {
+        match self {
+            Expr::ProgramCounter => {
+                *self = Expr::Reference(name.to_owned());
+                true
+            },
+            Expr::Unary(_, expr) => expr.replace_program_counter(name),
+            Expr::Binary(_, left, right) => left.replace_program_counter(name) | right.replace_program_counter(name),
+            _ => false
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub enum InstrInfoRegister {
     None,
     X,
-    Y
+    Y,
+    S,
+
+    /* Stack relative indirect indexed, (sr,S),Y */
+    SY
+}
+
+/* Address size prefix, 'a:' forces absolute and 'z:' forces zero page (ex. 'LDA a:$10') */
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OperandSize {
+    Auto,
+    Byte,
+    Word
 }
 
 #[derive(Debug, PartialEq)]
@@ -29,29 +283,37 @@ pub struct InstrInfo {
     pub value: InstrValue,
     pub is_immediate: bool,
     pub in_parenthesis: bool,
-    pub register: InstrInfoRegister
+    pub in_bracket: bool,
+    pub register: InstrInfoRegister,
+    pub size: OperandSize
 }
 
 #[derive(Debug, Copy, Clone)]
 pub enum BranchType {
     Generic,
-    Local
+    Local,
+    /* Generated by compiler, not changing local branch scope */
+    Internal
 }
 
 #[derive(Debug)]
 pub enum Ast {
     InstrImplied(usize),
     Instr(usize, InstrInfo),
+    InstrPair(usize, Expr, Expr),
     Branch(String, BranchType),
-    Directive(DirectiveEnum, Vec<DirectiveValue>)
+    Directive(DirectiveEnum, Vec<DirectiveValue>),
+    Include(usize)
 }
 
 #[derive(Debug)]
 pub struct AstInfo {
+    pub file_id: usize,
     pub line: usize,
     pub column: usize,
     pub ast: Ast,
     pub end: usize,
+    pub macro_call: Option<Rc<MacroCall>>
 }
 
 #[derive(Debug, Error)]
@@ -74,7 +336,13 @@ pub enum AstGeneratorError {
     IOError(#[from] std::io::Error),
 
     #[error("'{0}' reference already defined)")]
-    ReferenceAlreadyDefined(String)
+    ReferenceAlreadyDefined(String),
+
+    #[error("'{0}' macro already defined)")]
+    MacroAlreadyDefined(String),
+
+    #[error("Included file could not be parsed ({0})")]
+    ParseError(#[from] ParseError)
 }
 
 impl AstGeneratorError {
@@ -84,11 +352,47 @@ impl AstGeneratorError {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct MacroParameter {
+    pub name: String,
+    pub default: Option<Vec<TokenInfo>>
+}
+
+#[derive(Debug, Clone)]
+pub struct MacroInfo {
+    pub parameters: Vec<MacroParameter>,
+    pub tokens: Vec<TokenInfo>
+}
+
+#[derive(Debug)]
+pub struct ConditionInfo {
+    pub token_index: usize,
+    pub parent_active: bool,
+    pub active: bool,
+    pub taken: bool,
+    pub has_else: bool
+}
+
 #[derive(Debug)]
 pub struct AstGenerator {
     pub index: Cell<usize>,
     pub(crate) size: Cell<usize>,
-    pub include_asm: RefCell<Option<DirectiveValue>>
+    pub include_asm: RefCell<Option<DirectiveValue>>,
+    pub macros: RefCell<HashMap<String, MacroInfo>>,
+    pub expansion: RefCell<Option<Vec<TokenInfo>>>,
+    pub conditions: RefCell<Vec<ConditionInfo>>,
+    pub(crate) total_expansions: Cell<usize>,
+    pub(crate) total_repeat_tokens: Cell<usize>,
+    pub total_errors: Cell<usize>,
+
+    /* Parse error of an included file, it is already printed by the parser of the file */
+    include_error: RefCell<Option<ParseError>>,
+
+    /* '.rept' with a wrong header, its body is skipped after the error */
+    failed_repeat: Cell<Option<usize>>,
+
+    /* Mnemonics of the other CPUs are names, '.cpu' changes them for the following lines */
+    pub(crate) cpu: Cell<CpuType>
 }
 
 impl AstGenerator {
@@ -96,7 +400,16 @@ impl AstGenerator {
         Self {
             index: Cell::new(0),
             size: Cell::new(0),
-            include_asm: Default::default()
+            include_asm: Default::default(),
+            macros: Default::default(),
+            expansion: Default::default(),
+            conditions: Default::default(),
+            total_expansions: Cell::new(0),
+            total_repeat_tokens: Cell::new(0),
+            total_errors: Cell::new(0),
+            include_error: Default::default(),
+            failed_repeat: Cell::new(None),
+            cpu: Default::default()
         }
     }
     
@@ -156,13 +469,62 @@ impl AstGenerator {
         }
     }
 
-    fn eat_text(&self, context: &Context) -> Result<String, AstGeneratorError> {
-        let token_index= self.eat()?;
-        let token = &context.tokens.borrow()[token_index];
-        match &token.token {
-            Token::Keyword(text) => Ok(text.clone()),
-            Token::LocalBranch(text) => Ok(text.clone()),
-            _ => Err(AstGeneratorError::syntax_issue(context, token_index, "Expected text".to_string()))
+    pub(crate) fn parse_expression(&self, context: &Context) -> Result<Expr, AstGeneratorError> {
+        self.parse_binary_expression(context, 0)
+    }
+
+    fn parse_binary_expression(&self, context: &Context, min_precedence: u8) -> Result<Expr, AstGeneratorError> {
+        let mut left = self.parse_unary_expression(context)?;
+
+        loop {
+            self.cleanup_space(context)?;
+
+            let operator = match self.peek() {
+                Ok(token_index) => match &context.tokens.borrow()[token_index].token {
+                    Token::Operator(operator) => BinaryOperator::from_operator(*operator),
+                    _ => None
+                },
+                Err(_) => None
+            };
+
+            let operator = match operator {
+                Some(operator) if operator.precedence() >= min_precedence => operator,
+                _ => break
+            };
+
+            self.eat()?;
+            let right = self.parse_binary_expression(context, operator.precedence() + 1)?;
+            left = Expr::Binary(operator, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary_expression(&self, context: &Context) -> Result<Expr, AstGeneratorError> {
+        self.cleanup_space(context)?;
+        let token_index = self.eat()?;
+        let token = context.tokens.borrow()[token_index].token.clone();
+
+        match token {
+            Token::Operator(Operator::Minus) => Ok(Expr::Unary(UnaryOperator::Negate, Box::new(self.parse_unary_expression(context)?))),
+            Token::Operator(Operator::Not) => Ok(Expr::Unary(UnaryOperator::Not, Box::new(self.parse_unary_expression(context)?))),
+            Token::Operator(Operator::LessThan) => Ok(Expr::Unary(UnaryOperator::LowByte, Box::new(self.parse_unary_expression(context)?))),
+            Token::Operator(Operator::GreaterThan) => Ok(Expr::Unary(UnaryOperator::HighByte, Box::new(self.parse_unary_expression(context)?))),
+            Token::Operator(Operator::Xor) => Ok(Expr::Unary(UnaryOperator::BankByte, Box::new(self.parse_unary_expression(context)?))),
+            Token::Operator(Operator::LogicalNot) => Ok(Expr::Unary(UnaryOperator::LogicalNot, Box::new(self.parse_unary_expression(context)?))),
+            Token::Byte(byte) => Ok(Expr::Byte(byte)),
+            Token::Word(word) => Ok(Expr::Word(word)),
+            Token::Long(long) => Ok(Expr::Long(long)),
+            Token::Keyword(keyword) => Ok(Expr::Reference(keyword)),
+            Token::LocalKeyword(keyword) => Ok(Expr::LocalReference(keyword)),
+            Token::ProgramCounter => Ok(Expr::ProgramCounter),
+            Token::OpenParenthesis => {
+                let expr = self.parse_expression(context)?;
+                self.cleanup_space(context)?;
+                self.eat_expected(context, TokenType::CloseParenthesis, AstGeneratorError::syntax_issue(context, token_index, "Expected ')'".to_string()))?;
+                Ok(expr)
+            },
+            _ => Err(AstGeneratorError::syntax_issue(context, token_index, "Expression expected".to_string()))
         }
     }
 
@@ -193,9 +555,11 @@ impl AstGenerator {
             else {
                 /* Expected parseable token */
                 match value_token {
-                    Some(Token::Keyword(keyword)) => { values.push(DirectiveValue::Reference(keyword.clone())); token_found = true; },
-                    Some(Token::Word(number)) => { values.push(DirectiveValue::Word(*number)); token_found = true; },
-                    Some(Token::Byte(number)) => { values.push(DirectiveValue::Byte(*number)); token_found = true; },
+                    Some(Token::Keyword(_)) | Some(Token::LocalKeyword(_)) | Some(Token::Long(_)) | Some(Token::Word(_)) | Some(Token::Byte(_)) | Some(Token::OpenParenthesis) | Some(Token::Operator(_)) | Some(Token::ProgramCounter) => {
+                        self.index.set(value_index);
+                        values.push(DirectiveValue::from(self.parse_expression(context)?));
+                        token_found = true;
+                    },
                     Some(Token::String(string)) => { values.push(DirectiveValue::String(string.clone())); token_found = true; },
                     Some(Token::NewLine(_)) => finish = true,
                     Some(Token::Comment(_)) => finish = true,
@@ -222,9 +586,17 @@ impl AstGenerator {
     fn generate_directive(&self, context: &Context, token_index: usize, directive_name: &str) -> Result<(), AstGeneratorError> {
         let directive_name = directive_name.to_uppercase();
         if let Some(directive) = SYSTEM_DIRECTIVES.iter().find(|item| item.name == &directive_name[..]) {
+            match directive.directive {
+                DirectiveEnum::Macro => return self.generate_macro(context, token_index),
+                DirectiveEnum::Rept => return self.generate_repeat(context, token_index),
+                DirectiveEnum::Endr => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.endr' without '.rept'".to_string())),
+                DirectiveEnum::If | DirectiveEnum::Ifdef | DirectiveEnum::Ifndef | DirectiveEnum::Elseif | DirectiveEnum::Else | DirectiveEnum::Endif => return self.generate_condition(context, token_index, directive.directive),
+                DirectiveEnum::Endm => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.endm' without '.macro'".to_string())),
+                _ => ()
+            };
 
             let values = self.parse_list(context, |directive_type| -> bool {
-                return directive_type == DirectiveType::Reference || directive.values.iter().any(|mode| *mode == directive_type)
+                directive_type == DirectiveType::Reference || directive_type == DirectiveType::Expression || directive.values.contains(&directive_type)
             })?;
 
             match directive.size {
@@ -249,8 +621,14 @@ impl AstGenerator {
                 return Err(AstGeneratorError::syntax_issue(context, token_index, "Missing information".to_string()))
             }
 
-            match directive.directive {
-                DirectiveEnum::Include => *self.include_asm.borrow_mut() = Some(values[0].clone()),
+            match (directive.directive, &values[..]) {
+                (DirectiveEnum::Include, _) => *self.include_asm.borrow_mut() = Some(values[0].clone()),
+                (DirectiveEnum::Cpu, [DirectiveValue::String(name)]) => {
+                    if let Some(cpu) = CpuType::from_name(name) {
+                        self.cpu.set(cpu);
+                    }
+                    context.add_ast(token_index, Ast::Directive(directive.directive, values))
+                },
                 _ => context.add_ast(token_index, Ast::Directive(directive.directive, values))
             }
 
@@ -260,6 +638,478 @@ impl AstGenerator {
         Ok(())
     }
 
+    /* Split tokens until end of the line by commas, spaces at the beginning and at the end are removed */
+    fn parse_macro_arguments(&self, context: &Context) -> Result<Vec<Vec<TokenInfo>>, AstGeneratorError> {
+        let tokens = context.tokens.borrow();
+        let mut arguments = Vec::new();
+        let mut argument = Vec::new();
+        let mut depth = 0;
+
+        while let Ok(token_index) = self.peek() {
+            let token = &tokens[token_index];
+
+            match token.token {
+                Token::NewLine(_) | Token::Comment(_) | Token::End => break,
+                Token::Comma if depth == 0 => arguments.push(std::mem::take(&mut argument)),
+                Token::OpenParenthesis => { depth += 1; argument.push(token.clone()) },
+                Token::CloseParenthesis => { depth -= 1; argument.push(token.clone()) },
+                Token::Space(_) if argument.is_empty() => (),
+                _ => argument.push(token.clone())
+            };
+            self.eat()?;
+        }
+
+        if !argument.is_empty() || !arguments.is_empty() {
+            arguments.push(argument);
+        }
+
+        for argument in arguments.iter_mut() {
+            while let Some(TokenInfo { token: Token::Space(_), .. }) = argument.last() {
+                argument.pop();
+            }
+        }
+        Ok(arguments)
+    }
+
+    /* 'name = value' argument */
+    fn split_named_argument(argument: &[TokenInfo]) -> Option<(String, Vec<TokenInfo>)> {
+        let mut items = argument.iter().enumerate().filter(|(_, item)| !matches!(item.token, Token::Space(_)));
+
+        match (items.next(), items.next()) {
+            (Some((_, TokenInfo { token: Token::Keyword(name), .. })), Some((position, TokenInfo { token: Token::Assign, .. }))) => {
+                let value = argument[position + 1..].iter().skip_while(|item| matches!(item.token, Token::Space(_))).cloned().collect();
+                Some((name.clone(), value))
+            },
+            _ => None
+        }
+    }
+
+    fn generate_macro(&self, context: &Context, token_index: usize) -> Result<(), AstGeneratorError> {
+        self.cleanup_space(context)?;
+
+        let name_index = self.eat()?;
+        let name = match &context.tokens.borrow()[name_index].token {
+            Token::Keyword(name) => name.clone(),
+            _ => return Err(AstGeneratorError::syntax_issue(context, name_index, "Macro name expected".to_string()))
+        };
+
+        let mut parameters: Vec<MacroParameter> = Vec::new();
+        for argument in self.parse_macro_arguments(context)? {
+            let parameter = match Self::split_named_argument(&argument) {
+                Some((_, value)) if value.is_empty() => return Err(AstGeneratorError::syntax_issue(context, name_index, "Default value expected".to_string())),
+                Some((name, value)) => MacroParameter { name, default: Some(value) },
+                None => match &argument[..] {
+                    [TokenInfo { token: Token::Keyword(name), .. }] => MacroParameter { name: name.clone(), default: None },
+                    _ => return Err(AstGeneratorError::syntax_issue(context, name_index, "Parameter name expected".to_string()))
+                }
+            };
+
+            /* Register names are not substituted, 'LDA $10,X' stays indexed in the macro body */
+            if ["a", "x", "y", "s"].iter().any(|register| parameter.name.eq_ignore_ascii_case(register)) {
+                return Err(AstGeneratorError::syntax_issue(context, name_index, format!("'{}' is a register name and could not be a parameter", parameter.name)));
+            }
+
+            if parameters.iter().any(|item| item.name == parameter.name) {
+                return Err(AstGeneratorError::syntax_issue(context, name_index, format!("'{}' parameter already defined", parameter.name)));
+            }
+            parameters.push(parameter);
+        }
+
+        let tokens = context.tokens.borrow();
+        let mut body = Vec::new();
+        let mut body_started = false;
+
+        loop {
+            let body_index = match self.eat() {
+                Ok(body_index) => body_index,
+                Err(_) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.endm' expected".to_string()))
+            };
+            let token = &tokens[body_index];
+
+            match &token.token {
+                Token::Directive(directive) if find_directive(directive) == Some(DirectiveEnum::Endm) => break,
+                Token::Directive(directive) if find_directive(directive) == Some(DirectiveEnum::Macro) => return Err(AstGeneratorError::syntax_issue(context, body_index, "Macro can not be defined in macro".to_string())),
+                Token::End => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.endm' expected".to_string())),
+                Token::NewLine(_) if !body_started => body_started = true,
+                _ if body_started => body.push(token.clone()),
+                _ => ()
+            };
+        }
+
+        let has_macro = self.macros.borrow_mut().insert(name.clone(), MacroInfo { parameters, tokens: body }).is_some();
+        if has_macro {
+            return Err(AstGeneratorError::MacroAlreadyDefined(name));
+        }
+        Ok(())
+    }
+
+    fn expand_macro(&self, context: &Context, token_index: usize, name: &str) -> Result<(), AstGeneratorError> {
+        let info = match self.macros.borrow().get(name) {
+            Some(info) => info.clone(),
+            None => return Err(AstGeneratorError::InternalError)
+        };
+
+        let call_token = context.tokens.borrow()[token_index].clone();
+        let call = Rc::new(MacroCall {
+            name: name.to_owned(),
+            line: call_token.line,
+            column: call_token.column,
+            end: call_token.end,
+            file_id: call_token.file_id,
+            parent: call_token.macro_call.clone()
+        });
+
+        if call.depth() > MAX_MACRO_DEPTH {
+            return Err(AstGeneratorError::syntax_issue(context, token_index, format!("Macro depth limit ({}) exceeded", MAX_MACRO_DEPTH)));
+        }
+
+        let mut values: Vec<Option<Vec<TokenInfo>>> = vec![None; info.parameters.len()];
+        let mut position = 0;
+
+        for argument in self.parse_macro_arguments(context)? {
+            if let Some((parameter_name, value)) = Self::split_named_argument(&argument) {
+                if let Some(index) = info.parameters.iter().position(|parameter| parameter.name == parameter_name) {
+                    values[index] = Some(value);
+                    continue;
+                }
+            }
+
+            if position >= values.len() {
+                return Err(AstGeneratorError::syntax_issue(context, token_index, format!("Too many arguments for '{}' macro", name)));
+            }
+
+            if !argument.is_empty() {
+                values[position] = Some(argument);
+            }
+            position += 1;
+        }
+
+        for (value, parameter) in values.iter_mut().zip(info.parameters.iter()) {
+            if value.is_none() {
+                match &parameter.default {
+                    Some(default) => *value = Some(default.clone()),
+                    None => return Err(AstGeneratorError::syntax_issue(context, token_index, format!("Missing '{}' parameter", parameter.name)))
+                };
+            }
+        }
+
+        let expansion_id = self.next_expansion_id();
+        let local_branches = Self::defined_local_branches(&info.tokens);
+
+        let mut expansion = Vec::new();
+        for token in info.tokens.iter() {
+            let mut token = token.clone();
+
+            match &token.token {
+                Token::Keyword(keyword) => {
+                    if let Some(index) = info.parameters.iter().position(|parameter| &parameter.name == keyword) {
+                        for value in values[index].iter().flatten() {
+                            let mut value = value.clone();
+                            value.macro_call = Some(call.clone());
+                            expansion.push(value);
+                        }
+                        continue;
+                    }
+                },
+                _ => Self::rename_local_branch(&mut token.token, &local_branches, expansion_id)
+            };
+
+            token.macro_call = Some(call.clone());
+            expansion.push(token);
+        }
+
+        *self.expansion.borrow_mut() = Some(expansion);
+        Ok(())
+    }
+
+    fn generate_repeat(&self, context: &Context, token_index: usize) -> Result<(), AstGeneratorError> {
+        let (count, variable) = self.repeat_header(context, token_index).inspect_err(|_| self.failed_repeat.set(Some(token_index)))?;
+        let body = self.repeat_body(context, token_index, false)?;
+
+        let total_tokens = self.total_repeat_tokens.get().saturating_add(count.saturating_mul(body.len()));
+        if total_tokens > MAX_REPEAT_TOKENS {
+            return Err(AstGeneratorError::syntax_issue(context, token_index, format!("Repeated code can not be bigger than {} tokens", MAX_REPEAT_TOKENS)));
+        }
+        self.total_repeat_tokens.set(total_tokens);
+
+        let local_branches = Self::defined_local_branches(&body);
+        let mut expansion = Vec::new();
+
+        for iteration in 0..count {
+            let expansion_id = self.next_expansion_id();
+
+            for token in body.iter() {
+                let mut token = token.clone();
+
+                match &token.token {
+                    Token::Keyword(keyword) if Some(keyword) == variable.as_ref() => token.token = match iteration {
+                        0..=0xff => Token::Byte(iteration as u8),
+                        _ => Token::Word(iteration as u16)
+                    },
+                    _ => Self::rename_local_branch(&mut token.token, &local_branches, expansion_id)
+                };
+                expansion.push(token);
+            }
+        }
+
+        *self.expansion.borrow_mut() = Some(expansion);
+        Ok(())
+    }
+
+    /* Count and the optional iteration variable of '.rept' */
+    fn repeat_header(&self, context: &Context, token_index: usize) -> Result<(usize, Option<String>), AstGeneratorError> {
+        self.cleanup_space(context)?;
+        let expr = self.parse_expression(context)?;
+
+        let count = match self.evaluate_constant(context, &expr, &mut Vec::new()) {
+            Ok(result) if result.value < 0 => return Err(AstGeneratorError::syntax_issue(context, token_index, "Repeat count can not be negative".to_string())),
+            Ok(result) if result.value as usize > MAX_REPEAT_COUNT => return Err(AstGeneratorError::syntax_issue(context, token_index, format!("Repeat count can not be bigger than {}", MAX_REPEAT_COUNT))),
+            Ok(result) => result.value as usize,
+            Err(error) => return Err(AstGeneratorError::syntax_issue(context, token_index, error.to_string()))
+        };
+
+        /* Optional iteration variable */
+        let mut variable = None;
+        self.cleanup_space(context)?;
+
+        if let Ok(comma_index) = self.peek() {
+            if let Token::Comma = context.tokens.borrow()[comma_index].token {
+                self.eat()?;
+                self.cleanup_space(context)?;
+
+                let name_index = self.eat()?;
+                variable = match &context.tokens.borrow()[name_index].token {
+                    Token::Keyword(name) => Some(name.clone()),
+                    _ => return Err(AstGeneratorError::syntax_issue(context, name_index, "Variable name expected".to_string()))
+                };
+            }
+        }
+        self.expect_line_end(context)?;
+        Ok((count, variable))
+    }
+
+    /* Tokens after the header line until the matching '.endr' */
+    fn repeat_body(&self, context: &Context, token_index: usize, mut body_started: bool) -> Result<Vec<TokenInfo>, AstGeneratorError> {
+        let tokens = context.tokens.borrow();
+        let mut body = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let body_index = match self.eat() {
+                Ok(body_index) => body_index,
+                Err(_) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.endr' expected".to_string()))
+            };
+            let token = &tokens[body_index];
+
+            match &token.token {
+                Token::Directive(directive) => match find_directive(directive) {
+                    Some(DirectiveEnum::Rept) => depth += 1,
+                    Some(DirectiveEnum::Endr) if depth == 0 => break,
+                    Some(DirectiveEnum::Endr) => depth -= 1,
+                    _ => ()
+                },
+                Token::End => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.endr' expected".to_string())),
+                Token::NewLine(_) if !body_started => {
+                    body_started = true;
+                    continue;
+                },
+                _ => ()
+            };
+
+            if body_started {
+                body.push(token.clone());
+            }
+        }
+        Ok(body)
+    }
+
+    fn next_expansion_id(&self) -> usize {
+        self.total_expansions.set(self.total_expansions.get() + 1);
+        self.total_expansions.get()
+    }
+
+    fn defined_local_branches(tokens: &[TokenInfo]) -> Vec<String> {
+        tokens.iter().filter_map(|item| match &item.token {
+            Token::LocalBranch(name) => Some(name.clone()),
+            _ => None
+        }).collect()
+    }
+
+    /* Local branches defined in expanded tokens are unique for every expansion */
+    fn rename_local_branch(token: &mut Token, local_branches: &[String], expansion_id: usize) {
+        match token {
+            Token::LocalBranch(name) | Token::LocalKeyword(name) if local_branches.contains(name) => *name = format!("{}#{}", name, expansion_id),
+            _ => ()
+        };
+    }
+
+    fn process_expansion(&self, context: &Context) {
+        if let Some(expansion) = self.expansion.replace(None) {
+            let current_position = self.index.get();
+            let mut tokens = context.tokens.borrow_mut();
+            tokens.splice(current_position..current_position, expansion);
+            self.size.set(tokens.len());
+        }
+    }
+
+    /* Parser tokenizes every mnemonic as an instruction, the line is fixed for the CPU selected when it is reached */
+    fn classify_mnemonics(&self, context: &Context, token_index: usize) {
+        let mut tokens = context.tokens.borrow_mut();
+        let cpu = self.cpu.get();
+
+        for item in tokens.iter_mut().skip(token_index) {
+            match &item.token {
+                Token::NewLine(_) | Token::End => break,
+                Token::Instr(position) if !is_available(*position, cpu) => {
+                    let text = context.code_files.borrow().get(item.file_id).and_then(|file| line_text(&file.data, item.line).get(item.column..item.end).map(str::to_string));
+                    item.token = Token::Keyword(text.unwrap_or_else(|| INSTS[*position].to_string()));
+                },
+                Token::Keyword(name) => if let Some(position) = find_instr(&name.to_uppercase()).filter(|position| is_available(*position, cpu)) {
+                    item.token = Token::Instr(position);
+                },
+                _ => ()
+            };
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.conditions.borrow().last().map(|condition| condition.active).unwrap_or(true)
+    }
+
+    fn is_condition_directive(&self, context: &Context, token_index: usize) -> bool {
+        match &context.tokens.borrow()[token_index].token {
+            Token::Directive(name) => matches!(find_directive(name), Some(DirectiveEnum::If | DirectiveEnum::Ifdef | DirectiveEnum::Ifndef | DirectiveEnum::Elseif | DirectiveEnum::Else | DirectiveEnum::Endif)),
+            _ => false
+        }
+    }
+
+    fn skip_line(&self, context: &Context) -> Result<(), AstGeneratorError> {
+        while let Ok(token_index) = self.peek() {
+            match context.tokens.borrow()[token_index].token {
+                Token::NewLine(_) | Token::Comment(_) | Token::End => break,
+                _ => self.eat()?
+            };
+        }
+        Ok(())
+    }
+
+    fn expect_line_end(&self, context: &Context) -> Result<(), AstGeneratorError> {
+        self.cleanup_space(context)?;
+
+        if let Ok(token_index) = self.peek() {
+            match &context.tokens.borrow()[token_index].token {
+                Token::NewLine(_) | Token::Comment(_) | Token::End => (),
+                token => return Err(AstGeneratorError::syntax_issue(context, token_index, format!("Unexpected syntax ({:?})", token)))
+            };
+        }
+        Ok(())
+    }
+
+    fn evaluate_constant(&self, context: &Context, expr: &Expr, stack: &mut Vec<String>) -> Result<ExprValue, CodeGeneratorError> {
+        expr.evaluate(&mut |symbol| match symbol {
+            Expr::Reference(name) => {
+                let expr = match context.references.borrow().get(name) {
+                    Some(values) if values.len() == 1 => values[0].as_expr().ok_or(CodeGeneratorError::ExpectedThis("number"))?,
+                    Some(_) => return Err(CodeGeneratorError::ExpectedThis("only one value in reference")),
+                    None => return Err(CodeGeneratorError::UnresolvedReference(name.clone()))
+                };
+
+                if stack.contains(name) {
+                    return Err(CodeGeneratorError::CircularReference(name.clone()));
+                }
+
+                stack.push(name.clone());
+                let result = self.evaluate_constant(context, &expr, stack);
+                stack.pop();
+                result
+            },
+            _ => Err(CodeGeneratorError::ExpectedThis("const"))
+        })
+    }
+
+    /* Conditions are evaluated with the consts defined before, forward references and branches are not known yet */
+    fn parse_condition(&self, context: &Context, token_index: usize) -> Result<bool, AstGeneratorError> {
+        self.cleanup_space(context)?;
+        let expr = self.parse_expression(context)?;
+        self.expect_line_end(context)?;
+
+        match self.evaluate_constant(context, &expr, &mut Vec::new()) {
+            Ok(result) => Ok(result.value != 0),
+            Err(CodeGeneratorError::UnresolvedReference(name)) => Err(AstGeneratorError::syntax_issue(context, token_index, format!("'{}' must be a const defined before the condition", name))),
+            Err(error) => Err(AstGeneratorError::syntax_issue(context, token_index, error.to_string()))
+        }
+    }
+
+    fn parse_defined(&self, context: &Context) -> Result<bool, AstGeneratorError> {
+        self.cleanup_space(context)?;
+        let name_index = self.eat()?;
+
+        let name = match &context.tokens.borrow()[name_index].token {
+            Token::Keyword(name) => name.clone(),
+            _ => return Err(AstGeneratorError::syntax_issue(context, name_index, "Name expected".to_string()))
+        };
+        self.expect_line_end(context)?;
+
+        let is_branch = context.asts.borrow().iter().any(|item| matches!(&item.ast, Ast::Branch(branch, BranchType::Generic) if branch == &name));
+        Ok(is_branch || context.references.borrow().contains_key(&name) || self.macros.borrow().contains_key(&name))
+    }
+
+    fn generate_condition(&self, context: &Context, token_index: usize, directive: DirectiveEnum) -> Result<(), AstGeneratorError> {
+        match directive {
+            DirectiveEnum::If | DirectiveEnum::Ifdef | DirectiveEnum::Ifndef => {
+                let parent_active = self.is_active();
+                let result = match (parent_active, directive) {
+                    (false, _) => self.skip_line(context).map(|_| false),
+                    (true, DirectiveEnum::If) => self.parse_condition(context, token_index),
+                    (true, DirectiveEnum::Ifdef) => self.parse_defined(context),
+                    (true, _) => self.parse_defined(context).map(|defined| !defined)
+                };
+
+                /* A wrong condition opens an inactive block, so its body and '.endif' are not reported too */
+                let active = matches!(result, Ok(true));
+                self.conditions.borrow_mut().push(ConditionInfo { token_index, parent_active, active, taken: !matches!(result, Ok(false)) || !parent_active, has_else: false });
+                result?;
+            },
+            DirectiveEnum::Elseif => {
+                let taken = match self.conditions.borrow().last() {
+                    Some(condition) if condition.has_else => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.elseif' after '.else'".to_string())),
+                    Some(condition) => condition.taken,
+                    None => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.elseif' without '.if'".to_string()))
+                };
+
+                let result = match taken {
+                    true => self.skip_line(context).map(|_| false),
+                    false => self.parse_condition(context, token_index)
+                };
+
+                if let Some(condition) = self.conditions.borrow_mut().last_mut() {
+                    condition.active = matches!(result, Ok(true));
+                    condition.taken |= !matches!(result, Ok(false));
+                }
+                result?;
+            },
+            DirectiveEnum::Else => {
+                self.expect_line_end(context)?;
+                match self.conditions.borrow_mut().last_mut() {
+                    Some(condition) if condition.has_else => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.else' already defined".to_string())),
+                    Some(condition) => {
+                        condition.active = !condition.taken && condition.parent_active;
+                        condition.taken = true;
+                        condition.has_else = true;
+                    },
+                    None => return Err(AstGeneratorError::syntax_issue(context, token_index, "'.else' without '.if'".to_string()))
+                };
+            },
+            _ => {
+                self.expect_line_end(context)?;
+                if self.conditions.borrow_mut().pop().is_none() {
+                    return Err(AstGeneratorError::syntax_issue(context, token_index, "'.endif' without '.if'".to_string()));
+                }
+            }
+        };
+        Ok(())
+    }
+
     fn process_include(&self, context: &Context, token_index: usize) -> Result<(), AstGeneratorError> {
         let include_asm = self.include_asm.replace(None);
         let mut file_path = PathBuf::new();
@@ -270,9 +1120,16 @@ impl AstGenerator {
                 _ => return Err(AstGeneratorError::syntax_issue(context, token_index, "Path expected as a string".to_string()))
             };
     
+            let (file_id, line) = {
+                let token = &context.tokens.borrow()[token_index];
+                (token.file_id, token.line)
+            };
+            let path = context.add_file(file_id, file_path);
+            context.code_files.borrow_mut()[context.last_file_id()].included_from = Some((file_id, line));
+
+            /* Included file location is kept for the listing */
+            context.add_ast(token_index, Ast::Include(context.last_file_id()));
             let mut tokens = context.tokens.borrow_mut();
-            let token = &tokens[token_index];
-            let path = context.add_file(token.file_id, file_path);
     
             if !context.silent {
                 info!("Importing {:?}", &path.as_os_str());
@@ -287,17 +1144,29 @@ impl AstGenerator {
 
             code.push(b'\n'); // Add new lines to end of the code file
     
-            let new_context = Context::default();
+            /* Code files are moved to the parser to print the errors with the include locations */
+            let new_context = Context {
+                silent: context.silent,
+                max_errors: context.max_errors,
+                code_files: RefCell::new(context.code_files.take()),
+                total_errors: Cell::new(context.total_errors.get()),
+                ..Default::default()
+            };
     
+            /* Parsed lines of the file are used even if there is an error */
             let mut parser = Parser::new(context.last_file_id(), &code[..], new_context);
-            parser.parse().unwrap();
+            if let Err(error) = parser.parse() {
+                self.include_error.borrow_mut().get_or_insert(error);
+            }
+            context.code_files.replace(parser.context.code_files.take());
+            context.total_errors.set(parser.context.total_errors.get());
     
             let new_context = parser.context;
     
             let new_tokens = new_context.tokens.borrow();
             let current_position = self.index.get();
     
-            if new_tokens.len() > 0 {
+            if !new_tokens.is_empty() {
                 for token in new_tokens.iter().take(new_tokens.len()-1).rev() {
                     tokens.insert(current_position, token.clone());
                 }
@@ -314,12 +1183,34 @@ impl AstGenerator {
         Ok(())
     }
 
-    fn generate_assign(&self, context: &Context, _: usize, name: &String) -> Result<(), AstGeneratorError> {
+    fn generate_assign(&self, context: &Context, token_index: usize, name: &String) -> Result<(), AstGeneratorError> {
         self.cleanup_space(context)?;
+
+        /* Mnemonic of another CPU is a keyword, it is only valid as a name */
+        let is_assign = self.peek().map(|index| matches!(context.tokens.borrow()[index].token, Token::Assign)).unwrap_or_default();
+        if let (false, Some(instr)) = (is_assign, find_instr(&name.to_uppercase())) {
+            return Err(AstGeneratorError::syntax_issue(context, token_index, CodeGeneratorError::InstructionNotAvailable(INSTS[instr], self.cpu.get().name()).to_string()));
+        }
+
         self.eat_assign(context)?;
         self.cleanup_space(context)?;
 
-        let values = self.parse_list(context, |_| true)?;
+        let mut values = self.parse_list(context, |_| true)?;
+
+        /* Program counter is captured where the const is defined */
+        let position_name = format!("*{}", token_index);
+        let mut has_program_counter = false;
+
+        for value in values.iter_mut() {
+            if let DirectiveValue::Expression(expr) = value {
+                has_program_counter |= expr.replace_program_counter(&position_name);
+            }
+        }
+
+        if has_program_counter {
+            self.generate_branch(context, token_index, &position_name, BranchType::Internal)?;
+        }
+
         let has_reference = context.references.borrow_mut().insert(name.to_owned(), values).is_some();
 
         if has_reference {
@@ -328,6 +1219,20 @@ impl AstGenerator {
         Ok(())
     }
 
+    fn generate_program_counter_assign(&self, context: &Context, token_index: usize) -> Result<(), AstGeneratorError> {
+        self.cleanup_space(context)?;
+        self.eat_assign(context)?;
+        self.cleanup_space(context)?;
+
+        let values = self.parse_list(context, |directive_type| directive_type != DirectiveType::String)?;
+        if values.len() != 1 {
+            return Err(AstGeneratorError::syntax_issue(context, token_index, "Expected 1 value(s)".to_string()));
+        }
+
+        context.add_ast(token_index, Ast::Directive(DirectiveEnum::Org, values));
+        Ok(())
+    }
+
     pub(crate) fn parse_instr_value(&self, context: &Context) -> Result<InstrInfo, AstGeneratorError> {
         self.cleanup_space(context)?;
         let tokens = context.tokens.borrow();
@@ -337,58 +1242,70 @@ impl AstGenerator {
 
         let mut inst_info = InstrInfo {
             in_parenthesis: false,
+            in_bracket: false,
             is_immediate: false,
             register: InstrInfoRegister::None,
-            value: InstrValue::Byte(0)
+            value: InstrValue::Byte(0),
+            size: OperandSize::Auto
         };
 
         let mut parenthesis_open = false;
+        let mut value_index = token_index;
+
+        match token.token {
+            Token::OpenParenthesis => inst_info.in_parenthesis = true,
+            Token::OpenSquareBracket => inst_info.in_bracket = true,
+            _ => ()
+        };
 
-        if let Token::OpenParenthesis = token.token {
-            inst_info.in_parenthesis = true;
+        if inst_info.in_parenthesis || inst_info.in_bracket {
             parenthesis_open = true;
 
             self.cleanup_space(context)?;
-            let token_index = self.eat()?;
-            token = &tokens[token_index];
+            value_index = self.eat()?;
+            token = &tokens[value_index];
+        }
+
+        /* '[value]' is long indirect address (65816) */
+        let (close_type, close_error) = match inst_info.in_bracket {
+            true => (TokenType::CloseSquareBracket, "Expected ']'"),
+            false => (TokenType::CloseParenthesis, "Expected ')'")
+        };
+
+        /* 'a:' and 'z:' are tokenized as branch */
+        if let Token::Branch(name) = &token.token {
+            inst_info.size = match name.as_str() {
+                "a" | "A" => OperandSize::Word,
+                "z" | "Z" => OperandSize::Byte,
+                _ => return Err(AstGeneratorError::syntax_issue(context, value_index, "Expected 'a:' or 'z:'".to_string()))
+            };
+
+            self.cleanup_space(context)?;
+            value_index = self.eat()?;
+            token = &tokens[value_index];
         }
 
         if let Token::Sharp = &token.token {
             inst_info.is_immediate = true;
 
-            let token_index = self.eat()?;
-            token = &tokens[token_index];
+            value_index = self.eat()?;
         }
 
-        match &token.token {
-            Token::Keyword(keyword) => {
-                let references = context.references.borrow();
-                if let Some(values) = references.get(keyword) {
-                    if values.len() != 1 {
-                        return Err(AstGeneratorError::syntax_issue(context, token_index, "Only one token required".to_string()))
-                    }
-    
-                    let first_value = &values[0];
-                    match first_value {
-                        DirectiveValue::Byte(byte) => inst_info.value = InstrValue::Byte(*byte),
-                        DirectiveValue::Word(word) => inst_info.value = InstrValue::Word(*word),
-                        _ => return Err(AstGeneratorError::syntax_issue(context, token_index, "Invalid token for number".to_string()))
-                    };
-                } else {
-                    inst_info.value = InstrValue::Reference(keyword.to_owned());
-                }
-            },
-            Token::LocalKeyword(keyword) => inst_info.value = InstrValue::LocalReference(keyword.to_owned()),
-            Token::Byte(byte) =>  inst_info.value = InstrValue::Byte(*byte),
-            Token::Word(word) => inst_info.value = InstrValue::Word(*word),
-            _ => return Err(AstGeneratorError::syntax_issue(context, token_index, "Invalid numbering number format".to_string()))
+        self.index.set(value_index);
+        inst_info.value = match self.parse_expression(context)? {
+            Expr::Reference(keyword) => InstrValue::Reference(keyword),
+            Expr::LocalReference(keyword) => InstrValue::LocalReference(keyword),
+            Expr::Byte(byte) => InstrValue::Byte(byte),
+            Expr::Word(word) => InstrValue::Word(word),
+            Expr::Long(long) => InstrValue::Long(long),
+            expr => InstrValue::Expression(expr)
         };
         
         self.cleanup_space(context)?;
 
         if let Ok(token_index) = self.peek() {
             token = &tokens[token_index];
-            if let Token::CloseParenthesis = token.token {
+            if parenthesis_open && TokenType::from(&token.token) == close_type {
                 let _ = self.eat()?;
                 parenthesis_open = false;
                 self.cleanup_space(context)?;
@@ -407,13 +1324,17 @@ impl AstGenerator {
                 match &token.token {
                     Token::Keyword(value) if value == "x" || value == "X" => inst_info.register = InstrInfoRegister::X,
                     Token::Keyword(value) if value == "y" || value == "Y" => inst_info.register = InstrInfoRegister::Y,
-                    _ => return Err(AstGeneratorError::syntax_issue(context, token_index, "Expected X or Y".to_string()))
+                    Token::Keyword(value) if value == "s" || value == "S" => inst_info.register = InstrInfoRegister::S,
+                    _ => return Err(AstGeneratorError::syntax_issue(context, token_index, "Expected X, Y or S".to_string()))
                 };
     
-                if parenthesis_open && inst_info.register == InstrInfoRegister::Y {
-                    return Err(AstGeneratorError::syntax_issue(context, token_index, "Expected X".to_string()))
+                if parenthesis_open && inst_info.in_bracket {
+                    return Err(AstGeneratorError::syntax_issue(context, token_index, close_error.to_string()))
+
+                } else if parenthesis_open && inst_info.register == InstrInfoRegister::Y {
+                    return Err(AstGeneratorError::syntax_issue(context, token_index, "Expected X or S".to_string()))
                 
-                } else if !parenthesis_open && inst_info.in_parenthesis && inst_info.register == InstrInfoRegister::X {
+                } else if !parenthesis_open && (inst_info.in_parenthesis || inst_info.in_bracket) && inst_info.register != InstrInfoRegister::Y {
                     return Err(AstGeneratorError::syntax_issue(context, token_index, "Expected Y".to_string()))
                 }
                 
@@ -424,17 +1345,32 @@ impl AstGenerator {
         self.cleanup_space(context)?;
 
         if parenthesis_open {
-            self.eat_expected(context, TokenType::CloseParenthesis, AstGeneratorError::syntax_issue(context, token_index, "Expected ')'".to_string()))?;
+            self.eat_expected(context, close_type, AstGeneratorError::syntax_issue(context, token_index, close_error.to_string()))?;
+
+            /* Stack relative indirect address is always indexed with Y, (sr,S),Y */
+            if inst_info.register == InstrInfoRegister::S {
+                self.cleanup_space(context)?;
+                self.eat_expected(context, TokenType::Comma, AstGeneratorError::syntax_issue(context, token_index, "Expected ',Y'".to_string()))?;
+                self.cleanup_space(context)?;
+
+                let register_index = self.eat()?;
+                match &tokens[register_index].token {
+                    Token::Keyword(value) if value == "y" || value == "Y" => inst_info.register = InstrInfoRegister::SY,
+                    _ => return Err(AstGeneratorError::syntax_issue(context, register_index, "Expected Y".to_string()))
+                };
+            }
         }
 
+        /* Immediate word is kept only if it is not in byte range, 65816 decides the operand size */
         if inst_info.is_immediate && !inst_info.in_parenthesis && inst_info.register == InstrInfoRegister::None {
-            if let InstrValue::Word(word) = inst_info.value {
+            if let InstrValue::Word(word @ 0..=0xff) = inst_info.value {
                 inst_info.value = InstrValue::Byte(word as u8);
             }
         }
 
+        /* Indexed indirect address is in zero page, only JMP ($1234,X) needs a word */
         if !inst_info.is_immediate && inst_info.in_parenthesis && inst_info.register != InstrInfoRegister::None {
-            if let InstrValue::Word(word) = inst_info.value {
+            if let InstrValue::Word(word @ 0..=0xff) = inst_info.value {
                 inst_info.value = InstrValue::Byte(word as u8);
             }
         }
@@ -442,13 +1378,57 @@ impl AstGenerator {
         Ok(inst_info)
     }
 
+    /* Instruction without operand, 'A' is accepted as operand for the accumulator instructions (ex. 'ASL' or 'ASL A') */
+    fn parse_implied_operand(&self, context: &Context, accumulator: bool) -> bool {
+        let tokens = context.tokens.borrow();
+        let skip_space = |index: usize| match tokens.get(index).map(|item| &item.token) {
+            Some(Token::Space(_)) => index + 1,
+            _ => index
+        };
+        let is_line_end = |index: usize| matches!(tokens.get(index).map(|item| &item.token), None | Some(Token::NewLine(_) | Token::Comment(_) | Token::End));
+
+        let index = skip_space(self.index.get());
+        if is_line_end(index) {
+            return true;
+        }
+
+        match tokens.get(index).map(|item| &item.token) {
+            Some(Token::Keyword(name)) if accumulator && name.eq_ignore_ascii_case("a") && is_line_end(skip_space(index + 1)) => {
+                self.index.set(index + 1);
+                true
+            },
+            _ => false
+        }
+    }
+
+    /* '#' is optional for the block move banks (ex. MVN #$7E, #$7F) */
+    fn parse_pair_operand(&self, context: &Context) -> Result<Expr, AstGeneratorError> {
+        self.cleanup_space(context)?;
+        if let Ok(token_index) = self.peek() {
+            if let Token::Sharp = context.tokens.borrow()[token_index].token {
+                self.eat()?;
+            }
+        }
+        self.parse_expression(context)
+    }
+
     fn generate_code_block(&self, context: &Context, token_index: usize, positon: usize) -> Result<(), AstGeneratorError> {
 
-        if INSTS_SIZE[positon] == 1 {
+        if has_implied(positon) && self.parse_implied_operand(context, has_mode(positon, ModeType::Accumulator)) {
             context.add_ast(token_index,Ast::InstrImplied(positon));
         }
 
-        else if BRANCH_INSTS.contains(&positon) {
+        else if has_two_operands(positon) {
+            // Bit branch or block move inst
+            self.eat_space(context)?;
+            let first = self.parse_pair_operand(context)?;
+            self.cleanup_space(context)?;
+            self.eat_expected(context, TokenType::Comma, AstGeneratorError::syntax_issue(context, token_index, "Expected ','".to_string()))?;
+            let second = self.parse_pair_operand(context)?;
+            context.add_ast(token_index, Ast::InstrPair(positon, first, second));
+        }
+
+        else if is_branch(positon) {
             // Branch inst
             self.eat_space(context)?;
             let value = self.parse_instr_value(context)?;
@@ -457,6 +1437,7 @@ impl AstGenerator {
                 InstrValue::Byte(_) => context.add_ast(token_index, Ast::Instr(positon, value)),
                 InstrValue::Reference(_) => context.add_ast(token_index, Ast::Instr(positon, value)),
                 InstrValue::LocalReference(_) => context.add_ast(token_index, Ast::Instr(positon, value)),
+                InstrValue::Expression(_) => context.add_ast(token_index, Ast::Instr(positon, value)),
                 _ => return Err(AstGeneratorError::syntax_issue(context, token_index, "Relative number or branch name expected".to_string()))
             }
         }
@@ -470,57 +1451,143 @@ impl AstGenerator {
         Ok(())
     }
     
+    /* Returns true at the end of the tokens */
+    fn generate_token(&self, context: &Context, token_index: usize) -> Result<bool, AstGeneratorError> {
+        /* Tokens in false condition blocks are skipped */
+        if !self.is_active() && !self.is_condition_directive(context, token_index) {
+            return Ok(false);
+        }
+
+        self.classify_mnemonics(context, token_index);
+
+        {
+            let tokens = context.tokens.borrow();
+
+            match &tokens.get(token_index).map(|item| &item.token) {
+                Some(Token::Instr(positon)) => self.generate_code_block(context, token_index, *positon)?,
+                Some(Token::Keyword(keyword)) if self.macros.borrow().contains_key(keyword) => self.expand_macro(context, token_index, keyword)?,
+                Some(Token::Keyword(keyword)) => self.generate_assign(context, token_index, keyword)?,
+                Some(Token::ProgramCounter) => self.generate_program_counter_assign(context, token_index)?,
+                Some(Token::Directive(option)) => self.generate_directive(context, token_index, option)?,
+                Some(Token::Comment(_)) => (),
+                Some(Token::Branch(name)) => self.generate_branch(context, token_index, name, BranchType::Generic)?,
+                Some(Token::Byte(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Number not expected".to_string())),
+                Some(Token::Word(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Number not expected".to_string())),
+                Some(Token::Long(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Number not expected".to_string())),
+                Some(Token::NewLine(_)) => (),
+                Some(Token::Space(_)) => (),
+                Some(Token::OpenParenthesis) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'(' not expected".to_string())),
+                Some(Token::CloseParenthesis) => return Err(AstGeneratorError::syntax_issue(context, token_index, "')' not expected".to_string())),
+                Some(Token::OpenSquareBracket) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'[' not expected".to_string())),
+                Some(Token::CloseSquareBracket) => return Err(AstGeneratorError::syntax_issue(context, token_index, "']' not expected".to_string())),
+                Some(Token::Sharp) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'#' not expected".to_string())),
+                Some(Token::Operator(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Operator not expected".to_string())),
+                Some(Token::Assign) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'=' not expected".to_string())),
+                Some(Token::Comma) => return Err(AstGeneratorError::syntax_issue(context, token_index, "',' not expected".to_string())),
+                Some(Token::String(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "String not expected".to_string())),
+                Some(Token::LocalKeyword(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Unexpected local branch name".to_string())),
+                Some(Token::LocalBranch(name)) => self.generate_branch(context, token_index, name, BranchType::Local)?,
+                Some(Token::End) => return Ok(true),
+                None => return Err(AstGeneratorError::InternalError)
+            }
+        }
+
+        self.process_include(context, token_index)?;
+        self.process_expansion(context);
+        Ok(false)
+    }
+
+    /* The rest of the line is skipped after an error */
+    fn recover(&self, context: &Context) {
+        self.include_asm.replace(None);
+        self.expansion.replace(None);
+
+        let line_ended = matches!(self.index.get().checked_sub(1).and_then(|index| context.tokens.borrow().get(index).map(|item| TokenType::from(&item.token))), Some(TokenType::NewLine));
+        if !line_ended {
+            let _ = self.skip_line(context);
+        }
+
+        /* The body is skipped, so its '.endr' is not reported as an error too */
+        if let Some(token_index) = self.failed_repeat.take() {
+            let _ = self.repeat_body(context, token_index, line_ended);
+        }
+    }
+
+    fn print_error(&self, context: &Context, error: &AstGeneratorError) {
+        if context.silent {
+            return;
+        }
+
+        let tokens = context.tokens.borrow();
+        if let Some(token) = self.index.get().checked_sub(1).and_then(|index| tokens.get(index)) {
+            /* Syntax issues know their location, a block header is reported after reading the block */
+            let (line, column, end) = match error {
+                AstGeneratorError::SyntaxIssue { line, column, end, .. } => (*line, *column, *end),
+                _ => (token.line, token.column, token.end)
+            };
+
+            let code_files = context.code_files.borrow();
+            print_code_error(&code_files, token.file_id, error, line, column, end);
+            print_macro_calls(&code_files, &token.macro_call);
+        }
+    }
+
     fn inline_generate(&self, context: &Context) -> Result<(), AstGeneratorError> {
-        self.size.set(context.tokens.borrow().len());
-        let mut token_index = 0;
+        let mut first_error = None;
 
+        self.size.set(context.tokens.borrow().len());
         while self.size.get() > self.index.get() {
-            {
-                token_index = self.eat()?;
-                let tokens = context.tokens.borrow();
-
-                match &tokens.get(token_index).map(|item| &item.token) {
-                    Some(Token::Instr(positon)) => self.generate_code_block(context, token_index, *positon)?,
-                    Some(Token::Keyword(keyword)) => self.generate_assign(context, token_index, keyword)?,
-                    Some(Token::Directive(option)) => self.generate_directive(context, token_index, option)?,
-                    Some(Token::Comment(_)) => (),
-                    Some(Token::Branch(name)) => self.generate_branch(context, token_index, name, BranchType::Generic)?,
-                    Some(Token::Byte(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Number not expected".to_string())),
-                    Some(Token::Word(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Number not expected".to_string())),
-                    Some(Token::NewLine(_)) => (),
-                    Some(Token::Space(_)) => (),
-                    Some(Token::OpenParenthesis) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'(' not expected".to_string())),
-                    Some(Token::CloseParenthesis) => return Err(AstGeneratorError::syntax_issue(context, token_index, "')' not expected".to_string())),
-                    Some(Token::Sharp) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'#' not expected".to_string())),
-                    Some(Token::Assign) => return Err(AstGeneratorError::syntax_issue(context, token_index, "'=' not expected".to_string())),
-                    Some(Token::Comma) => return Err(AstGeneratorError::syntax_issue(context, token_index, "',' not expected".to_string())),
-                    Some(Token::String(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "String not expected".to_string())),
-                    Some(Token::LocalKeyword(_)) => return Err(AstGeneratorError::syntax_issue(context, token_index, "Unexpected local branch name".to_string())),
-                    Some(Token::LocalBranch(name)) => self.generate_branch(context, token_index, name, BranchType::Local)?,
-                    Some(Token::End) => break,
-                    None => return Err(AstGeneratorError::InternalError)
+            let token_index = self.eat()?;
+            let result = self.generate_token(context, token_index);
+
+            if let Some(error) = self.include_error.take() {
+                first_error.get_or_insert(error.into());
+            }
+
+            match result {
+                Ok(true) => break,
+                Ok(false) => (),
+                Err(error) => {
+                    if context.add_error() {
+                        self.print_error(context, &error);
+                    }
+                    self.total_errors.set(self.total_errors.get() + 1);
+                    first_error.get_or_insert(error);
+                    self.recover(context);
                 }
+            };
+
+            if context.error_limit_reached() {
+                break;
             }
+        }
 
-            self.process_include(context, token_index)?;
+        if let (Some(condition), false) = (self.conditions.borrow().last(), context.error_limit_reached()) {
+            self.index.set(condition.token_index + 1);
+            let error = AstGeneratorError::syntax_issue(context, condition.token_index, "'.endif' expected".to_string());
+            if context.add_error() {
+                self.print_error(context, &error);
+            }
+            self.total_errors.set(self.total_errors.get() + 1);
+            first_error.get_or_insert(error);
         }
 
-        Ok(())
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(())
+        }
     }
     
+    /* Errors are printed until the error limit, the first one is returned */
+    #[cfg(test)]
     pub fn generate(&self, context: Context) -> Result<Context, AstGeneratorError> {
-        match self.inline_generate(&context) {
-            Ok(_) => Ok(context),
-            Err(error) => {
-                let tokens = context.tokens.borrow();
-                let token = &tokens[self.index.get() - 1];
-
-                if !context.silent {
-                    let code_file = &context.code_files.borrow()[token.file_id];
-                    print_error(&code_file.data, &error, token.line, token.column, token.end);
-                }
-                Err(error)
-            }
-        }
+        self.generate_in_place(&context)?;
+        Ok(context)
+    }
+
+    /* Context is kept after an error, so the code generator could report its errors too */
+    pub fn generate_in_place(&self, context: &Context) -> Result<(), AstGeneratorError> {
+        self.cpu.set(context.cpu);
+        self.inline_generate(context)
     }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, str::Utf8Error};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, ops::{Range, RangeInclusive}, str::Utf8Error};
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Read;
@@ -9,10 +9,11 @@ use log::{info, warn}; // Use log crate when building application
 use std::{println as info, println as warn}; // Workaround to use prinltn! for logs.
 use thiserror::Error;
 
-use crate::ast::{InstrInfo, InstrValue, InstrInfoRegister};
+use crate::ast::{Expr, ExprValue, InstrInfo, InstrInfoRegister, InstrValue, OperandSize};
 use crate::context::Context;
-use crate::opcode::BRANCH_INSTS;
-use crate::tool::print_error;
+use crate::output::InesHeader;
+use crate::opcode::{find_instr, inverse_branch, is_accumulator_width, is_branch, is_index_width, operand_size, CpuType, ModeInfo, INSTS};
+use crate::tool::{print_code_error, print_macro_calls};
 use crate::{ast::{Ast, BranchType}, opcode::{ModeType, MODES}, directive::{DirectiveEnum, DirectiveValue}};
 
 #[derive(Error, Debug)]
@@ -25,10 +26,14 @@ pub enum CodeGeneratorError {
     IllegalOpcode,
     #[error("Number not applicable")]
     NumberNotApplicable,
-    #[error("Branch information not found")]
-    UnresolvedBranches,
-    #[error("Reference information not found")]
-    UnresolvedReference,
+    #[error("'{0}' reference information not found")]
+    UnresolvedReference(String),
+    #[error("'{0}' already defined")]
+    SymbolAlreadyDefined(String),
+    #[error("Address 0x{0:04X} is behind the current location")]
+    AddressAlreadyPassed(u32),
+    #[error("Addresses are not stable after {0} passes")]
+    NotConverged(usize),
     #[error("Expected &String")]
     StringExpected,
     #[error("IO Error ({0})")]
@@ -38,22 +43,63 @@ pub enum CodeGeneratorError {
     #[error("Expected {0}")]
     ExpectedThis(&'static str),
     #[error("{0}")]
-    ProgramFailed(String)
+    ProgramFailed(String),
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("'{0}' reference refers to itself")]
+    CircularReference(String),
+    #[error("'{0}' is not available for {1}")]
+    InstructionNotAvailable(&'static str, &'static str),
+    #[error("'{0}' does not support this addressing mode for {1}")]
+    ModeNotAvailable(&'static str, &'static str),
+    #[error("'{0}' is not a supported CPU")]
+    UnsupportedCpu(String),
+    #[error("Branch target is {0} bytes away, branch range is {1} to {2}")]
+    BranchOutOfRange(i64, i64, i64)
 }
 
+/* Every pass re-generates the whole program, forward references use the addresses found in the previous pass */
+pub const MAX_PASSES: usize = 16;
+
+const SHORT_BRANCH_RANGE: RangeInclusive<i64> = -128..=127;
+const LONG_BRANCH_RANGE: RangeInclusive<i64> = -32768..=32767;
+
 #[derive(Debug)]
 pub struct CodeGenerator {
     pub index: usize,
     pub size: usize,
     pub silent: bool,
-
-    pub start_point: u16,
+    pub cpu: CpuType,
+    pub relax_branches: bool,
+
+    pub current_cpu: CpuType,
+    pub current_relax_branches: bool,
+    pub accumulator_16: bool,
+    pub index_16: bool,
+    pub start_point: u32,
+    pub base_offset: i64,
+    pub program_counter: usize,
     pub fillvalue : u8,
+    pub scope: String,
     pub branches: HashMap<String, usize>,
     pub local_branches: HashMap<String, usize>,
-    pub unresolved_relative_jump: Vec<(String, usize, usize)>,
-    pub unresolved_absolute_jumps: Vec<(String, usize, usize)>,
-    pub unresolved_local_branches: Vec<(String, usize, usize)>
+    pub previous_branches: HashMap<String, usize>,
+    pub previous_local_branches: HashMap<String, usize>,
+    pub unresolved: RefCell<Vec<(String, usize)>>,
+    pub errors: Vec<(CodeGeneratorError, usize)>,
+    pub total_errors: usize,
+    pub warnings: Vec<String>,
+    pub ines: InesHeader,
+    pub chr_start: Option<usize>,
+    pub org_gaps: Vec<Range<usize>>,
+    pub label_offsets: HashMap<String, usize>,
+    pub ast_ranges: Vec<(usize, Range<usize>)>,
+
+    /* Branches written as inverted branch and JMP, they stay long in the next passes so the addresses converge */
+    pub relaxed_branches: HashSet<usize>,
+
+    /* Instructions with an address above zero page in a pass, they are not shrunk back to zero page in the next passes */
+    pub absolute_operands: HashSet<usize>
 }
 
 impl CodeGenerator {
@@ -62,13 +108,32 @@ impl CodeGenerator {
             index: 0,
             size: 0,
             silent: false,
+            cpu: Default::default(),
+            relax_branches: false,
+            current_cpu: Default::default(),
+            current_relax_branches: false,
+            accumulator_16: false,
+            index_16: false,
             start_point: Default::default(),
+            base_offset: 0,
+            program_counter: 0,
             fillvalue: 0x00,
+            scope: Default::default(),
             branches: Default::default(),
             local_branches: Default::default(),
-            unresolved_local_branches: Default::default(),
-            unresolved_relative_jump: Default::default(),
-            unresolved_absolute_jumps: Default::default(),
+            previous_branches: Default::default(),
+            previous_local_branches: Default::default(),
+            unresolved: Default::default(),
+            errors: Default::default(),
+            total_errors: 0,
+            warnings: Default::default(),
+            ines: Default::default(),
+            chr_start: None,
+            org_gaps: Default::default(),
+            label_offsets: Default::default(),
+            ast_ranges: Default::default(),
+            relaxed_branches: Default::default(),
+            absolute_operands: Default::default(),
         }
     }
 
@@ -85,167 +150,387 @@ impl CodeGenerator {
         Ok(self.index - 1)
     }
 
-    fn push_number(&mut self, target: &mut Vec<u8>, number: u16, mode: ModeType) -> Result<(), CodeGeneratorError> {
+    /* Run address of the next byte, .base changes it without moving the stored codes */
+    fn current_address(&self, target: &[u8]) -> usize {
+        (self.start_point as i64 + target.len() as i64 + self.base_offset) as usize
+    }
+
+    fn local_name(&self, name: &str) -> String {
+        format!("{}@{}", self.scope, name)
+    }
+
+    fn evaluate(&self, references: &HashMap<String, Vec<DirectiveValue>>, expr: &Expr) -> Result<ExprValue, CodeGeneratorError> {
+        self.evaluate_with_stack(references, expr, &mut Vec::new())
+    }
+
+    fn evaluate_with_stack(&self, references: &HashMap<String, Vec<DirectiveValue>>, expr: &Expr, stack: &mut Vec<String>) -> Result<ExprValue, CodeGeneratorError> {
+        expr.evaluate(&mut |symbol| self.resolve_symbol(references, symbol, stack))
+    }
+
+    fn resolve_branch(&self, name: String, branches: &HashMap<String, usize>, previous_branches: &HashMap<String, usize>) -> ExprValue {
+        match branches.get(&name).or_else(|| previous_branches.get(&name)) {
+            Some(address) => ExprValue::address(*address),
+            None => {
+                let mut unresolved = self.unresolved.borrow_mut();
+                let ast_index = self.index - 1;
+                if !unresolved.iter().any(|(item, index)| *item == name && *index == ast_index) {
+                    unresolved.push((name, ast_index));
+                }
+                ExprValue::unresolved()
+            }
+        }
+    }
+
+    fn resolve_symbol(&self, references: &HashMap<String, Vec<DirectiveValue>>, symbol: &Expr, stack: &mut Vec<String>) -> Result<ExprValue, CodeGeneratorError> {
+        match symbol {
+            Expr::Reference(name) => {
+                if let Some(values) = references.get(name) {
+                    if stack.contains(name) {
+                        return Err(CodeGeneratorError::CircularReference(name.clone()));
+                    }
+
+                    if values.len() != 1 {
+                        return Err(CodeGeneratorError::ExpectedThis("only one value in reference"));
+                    }
+
+                    let expr = match values[0].as_expr() {
+                        Some(expr) => expr,
+                        None => return Err(CodeGeneratorError::ExpectedThis("number"))
+                    };
+
+                    stack.push(name.clone());
+                    let result = self.evaluate_with_stack(references, &expr, stack);
+                    stack.pop();
+                    return result;
+                }
+
+                Ok(self.resolve_branch(name.clone(), &self.branches, &self.previous_branches))
+            },
+            Expr::ProgramCounter => Ok(ExprValue::address(self.program_counter)),
+            Expr::LocalReference(name) => Ok(self.resolve_branch(self.local_name(name), &self.local_branches, &self.previous_local_branches)),
+            _ => Err(CodeGeneratorError::InternalError)
+        }
+    }
+
+    fn directive_number(&self, references: &HashMap<String, Vec<DirectiveValue>>, value: &DirectiveValue) -> Result<ExprValue, CodeGeneratorError> {
+        match value.as_expr() {
+            Some(expr) => self.evaluate(references, &expr),
+            None => Err(CodeGeneratorError::ExpectedThis("number"))
+        }
+    }
+
+    fn push_number(&mut self, target: &mut Vec<u8>, number: u32, mode: ModeType) -> Result<(), CodeGeneratorError> {
         match mode {
-            ModeType::Relative | ModeType::Immediate | ModeType::ZeroPage | ModeType::ZeroPageX | ModeType::ZeroPageY | ModeType::IndirectX | ModeType::IndirectY => {
+            ModeType::Relative | ModeType::Immediate | ModeType::ZeroPage | ModeType::ZeroPageX | ModeType::ZeroPageY | ModeType::IndirectX | ModeType::IndirectY | ModeType::ZeroPageIndirect |
+            ModeType::DirectIndirectLong | ModeType::DirectIndirectLongY | ModeType::StackRelative | ModeType::StackRelativeIndirectY => {
+                target.push(number as u8);
+            }
+            ModeType::Implied | ModeType::Accumulator | ModeType::ZeroPageRelative | ModeType::BlockMove => return Err(CodeGeneratorError::NumberNotApplicable),
+            ModeType::Absolute | ModeType::AbsoluteX | ModeType::AbsoluteY | ModeType::Indirect | ModeType::AbsoluteIndirectX | ModeType::AbsoluteIndirectLong | ModeType::RelativeLong => {
                 target.push(number as u8);
+                target.push((number >> 8) as u8);
             }
-            ModeType::Implied => return Err(CodeGeneratorError::NumberNotApplicable),
-            ModeType::Accumulator => return Err(CodeGeneratorError::NumberNotApplicable),
-            ModeType::Absolute | ModeType::AbsoluteX | ModeType::AbsoluteY | ModeType::Indirect => {
+            ModeType::AbsoluteLong | ModeType::AbsoluteLongX => {
                 target.push(number as u8);
                 target.push((number >> 8) as u8);
+                target.push((number >> 16) as u8);
             }
         };
 
         Ok(())
     }
 
-    fn build_relative_jump(target: &mut [u8], ast_index: usize, reference: &String, branches: &mut HashMap<String, usize>, unresolved_jump: &mut Vec<(String, usize, usize)>) -> (u16, ModeType) {
-        match branches.get(reference) {
-            Some(branch_position) => ((*branch_position as i16 - (target.len() + 2) as i16) as u16, ModeType::Relative),
-            None => {
-                unresolved_jump.push((reference.clone(), target.len() + 1, ast_index));
-                (0, ModeType::Relative)
+    /* First available mode in the candidates for the selected CPU */
+    fn find_mode(&self, instr: usize, candidates: &[ModeType]) -> Result<&'static ModeInfo, CodeGeneratorError> {
+        let modes: &'static [ModeInfo] = MODES[instr];
+        let cpu = self.current_cpu.flag();
+
+        for candidate in candidates.iter() {
+            if let Some(info) = modes.iter().find(|info| info.mode == *candidate && info.cpus & cpu != 0) {
+                return Ok(info);
             }
         }
+
+        if !modes.iter().any(|info| info.cpus & cpu != 0) {
+            return Err(CodeGeneratorError::InstructionNotAvailable(INSTS[instr], self.current_cpu.name()));
+        }
+
+        if modes.iter().any(|info| candidates.contains(&info.mode)) {
+            return Err(CodeGeneratorError::ModeNotAvailable(INSTS[instr], self.current_cpu.name()));
+        }
+
+        Err(CodeGeneratorError::IllegalOpcode)
     }
 
-    fn build_absolute_jump(target: &mut [u8], ast_index: usize, reference: &String, branches: &mut HashMap<String, usize>, unresolved_jump: &mut Vec<(String, usize, usize)>) -> (u16, ModeType) {
-        match branches.get(reference) {
-            Some(branch_position) => (*branch_position as u16, ModeType::Absolute),
-            None => {
-                unresolved_jump.push((reference.clone(), target.len() + 1, ast_index));
-                (0, ModeType::Absolute)
-            }
+    /* 65816 immediate operand is 16 bit if the related register is 16 bit */
+    fn is_wide_immediate(&self, instr: usize) -> bool {
+        self.current_cpu == CpuType::Wdc65816 && ((self.accumulator_16 && is_accumulator_width(instr)) || (self.index_16 && is_index_width(instr)))
+    }
+
+    /* REP and SEP with constant operand change the register sizes */
+    fn track_register_width(&mut self, instr: usize, mode: ModeType, result: &ExprValue) {
+        if mode != ModeType::Immediate || !result.resolved {
+            return;
+        }
+
+        let set = match INSTS[instr] {
+            "REP" => false,
+            "SEP" => true,
+            _ => return
+        };
+
+        if result.value & 0x20 != 0 {
+            self.accumulator_16 = !set;
+        }
+
+        if result.value & 0x10 != 0 {
+            self.index_16 = !set;
         }
     }
 
-    fn generate_instr(&mut self, target: &mut Vec<u8>, ast_index: usize, instr: usize, value: &InstrInfo) -> Result<(), CodeGeneratorError> {
-        let modes = MODES[instr];
-        let mut found = false;
-        let relative_jump = BRANCH_INSTS.contains(&instr);
+    /* Out of range branches are written as inverted branch over JMP if the relaxation is enabled (ex. BNE far is BEQ *+5, JMP far) */
+    fn generate_relative(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, instr: usize, value: &InstrInfo) -> Result<(), CodeGeneratorError> {
+        let mode = self.find_mode(instr, &[ModeType::Relative, ModeType::RelativeLong])?;
+        let (instr_size, range) = match mode.mode {
+            ModeType::RelativeLong => (3, LONG_BRANCH_RANGE),
+            _ => (2, SHORT_BRANCH_RANGE)
+        };
 
-        let (number, mut possible_mode) = match &value.value {
-            InstrValue::Byte(byte) => (*byte as u16, ModeType::ZeroPage),
-            InstrValue::Word(word) => (*word, ModeType::Absolute),
-            InstrValue::Reference(reference) => match relative_jump {
-                true => Self::build_relative_jump(target, ast_index, reference, &mut self.branches, &mut self.unresolved_relative_jump),
-                false => Self::build_absolute_jump(target, ast_index, reference, &mut self.branches, &mut self.unresolved_absolute_jumps),
+        let result = match &value.value {
+            /* Raw offset */
+            InstrValue::Byte(byte) => {
+                target.push(mode.opcode);
+                return self.push_number(target, *byte as u32, mode.mode);
             },
-            InstrValue::LocalReference(reference) => match relative_jump {
-                true => Self::build_relative_jump(target, ast_index, reference, &mut self.local_branches, &mut self.unresolved_local_branches),
-                false => Self::build_absolute_jump(target, ast_index, reference, &mut self.local_branches, &mut self.unresolved_local_branches),
-            }
+            value => self.evaluate(references, &value.as_expr())?
         };
 
-        if value.in_parenthesis {
-            possible_mode = match value.register {
-                InstrInfoRegister::None => ModeType::Indirect,
-                InstrInfoRegister::X => ModeType::IndirectX,
-                InstrInfoRegister::Y => ModeType::IndirectY,
-            };
-        } else {
-            possible_mode = match value.register {
-                InstrInfoRegister::None => possible_mode,
-                InstrInfoRegister::X => match possible_mode {
-                    ModeType::ZeroPage => ModeType::ZeroPageX,
-                    _ => ModeType::AbsoluteX,
-                },
-                InstrInfoRegister::Y => match possible_mode {
-                    ModeType::ZeroPage => ModeType::ZeroPageY,
-                    _ => ModeType::AbsoluteY,
+        let ast_index = self.index - 1;
+        let offset = result.value - (self.current_address(target) as i64 + instr_size);
+        let out_of_range = result.resolved && !range.contains(&offset);
+        let relax = self.relaxed_branches.contains(&ast_index) || (out_of_range && self.current_relax_branches && mode.mode == ModeType::Relative);
+
+        if relax {
+            if let Some(jump) = find_instr("JMP").and_then(|jump| self.find_mode(jump, &[ModeType::Absolute]).ok()) {
+                /* BRA is always taken, only the JMP is needed */
+                let inverse = match INSTS[instr] {
+                    "BRA" => Some(None),
+                    _ => inverse_branch(instr).and_then(|inverse| self.find_mode(inverse, &[ModeType::Relative]).ok()).map(Some)
+                };
+
+                if let Some(inverse) = inverse {
+                    if let Some(inverse) = inverse {
+                        target.push(inverse.opcode);
+                        target.push(3);
+                    }
+
+                    target.push(jump.opcode);
+                    self.relaxed_branches.insert(ast_index);
+                    return self.push_number(target, result.value as u32, ModeType::Absolute);
                 }
-            };
+            }
         }
 
-        if value.is_immediate {
-            possible_mode = ModeType::Immediate;
-        }
+        /* Bytes are written before the error, so the following addresses are still correct */
+        target.push(mode.opcode);
+        self.push_number(target, offset as u32, mode.mode)?;
 
-        if BRANCH_INSTS.contains(&instr) {
-            possible_mode = ModeType::Relative;
+        match out_of_range {
+            true => Err(CodeGeneratorError::BranchOutOfRange(offset, *range.start(), *range.end())),
+            false => Ok(())
         }
+    }
 
-        for search_mode in modes.iter() {
-            if search_mode.mode == possible_mode {
-                target.push(search_mode.opcode);
-                self.push_number(target, number, possible_mode)?;
-                found = true;
-                break;
+    /* Addresses below $100 are zero page, unresolved addresses are absolute until they are resolved */
+    fn operand_size(&mut self, size: OperandSize, result: &ExprValue) -> Result<(bool, bool), CodeGeneratorError> {
+        let ast_index = self.index - 1;
+        let zero_page = (0..=0xff).contains(&result.value);
+
+        match size {
+            OperandSize::Byte if result.resolved && !zero_page => Err(CodeGeneratorError::ExpectedThis("zero page address")),
+            OperandSize::Byte => Ok((false, false)),
+            OperandSize::Word => Ok((false, true)),
+            OperandSize::Auto if result.is_long || !result.is_word => Ok((result.is_long, result.is_word)),
+            OperandSize::Auto if !result.resolved => Ok((false, true)),
+            OperandSize::Auto if zero_page && !self.absolute_operands.contains(&ast_index) => Ok((false, false)),
+            OperandSize::Auto => {
+                self.absolute_operands.insert(ast_index);
+                Ok((false, true))
             }
         }
+    }
+
+    fn generate_instr(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, instr: usize, value: &InstrInfo) -> Result<(), CodeGeneratorError> {
+        if is_branch(instr) {
+            return self.generate_relative(target, references, instr, value);
+        }
 
-        if !found {
-            return Err(CodeGeneratorError::IllegalOpcode)
+        let result = self.evaluate(references, &value.value.as_expr())?;
+        if !result.fits_byte() && !result.is_word {
+            return Err(CodeGeneratorError::ExpectedThis("byte"));
+        }
+
+        /* Long address could be used as 16 bit address if it is in the current bank (ex. JSR) */
+        let same_bank = (result.value >> 16) == (self.program_counter >> 16) as i64;
+        let (is_long, is_word) = self.operand_size(value.size, &result)?;
+
+        /* Zero page modes are preferred, bigger modes are used if the instruction has no zero page version */
+        let candidates: &[ModeType] = match (value.is_immediate, value.in_parenthesis, value.in_bracket, value.register, is_long, is_word) {
+            (true, _, _, _, _, _) => &[ModeType::Immediate],
+            (false, true, _, InstrInfoRegister::None, _, false) => &[ModeType::ZeroPageIndirect, ModeType::Indirect],
+            (false, true, _, InstrInfoRegister::None, _, true) => &[ModeType::Indirect],
+            (false, true, _, InstrInfoRegister::X, _, false) => &[ModeType::IndirectX, ModeType::AbsoluteIndirectX],
+            (false, true, _, InstrInfoRegister::X, _, true) => &[ModeType::AbsoluteIndirectX],
+            (false, true, _, InstrInfoRegister::Y, _, _) => &[ModeType::IndirectY],
+            (false, true, _, InstrInfoRegister::S | InstrInfoRegister::SY, _, _) => &[ModeType::StackRelativeIndirectY],
+            (false, false, true, InstrInfoRegister::None, _, false) => &[ModeType::DirectIndirectLong, ModeType::AbsoluteIndirectLong],
+            (false, false, true, InstrInfoRegister::None, _, true) => &[ModeType::AbsoluteIndirectLong],
+            (false, false, true, _, _, _) => &[ModeType::DirectIndirectLongY],
+            (false, false, false, InstrInfoRegister::None, false, false) => &[ModeType::ZeroPage, ModeType::Absolute, ModeType::AbsoluteLong],
+            (false, false, false, InstrInfoRegister::None, false, true) => &[ModeType::Absolute, ModeType::AbsoluteLong],
+            (false, false, false, InstrInfoRegister::None, true, _) if same_bank => &[ModeType::AbsoluteLong, ModeType::Absolute],
+            (false, false, false, InstrInfoRegister::None, true, _) => &[ModeType::AbsoluteLong],
+            (false, false, false, InstrInfoRegister::X, false, false) => &[ModeType::ZeroPageX, ModeType::AbsoluteX, ModeType::AbsoluteLongX],
+            (false, false, false, InstrInfoRegister::X, false, true) => &[ModeType::AbsoluteX, ModeType::AbsoluteLongX],
+            (false, false, false, InstrInfoRegister::X, true, _) if same_bank => &[ModeType::AbsoluteLongX, ModeType::AbsoluteX],
+            (false, false, false, InstrInfoRegister::X, true, _) => &[ModeType::AbsoluteLongX],
+            (false, false, false, InstrInfoRegister::Y, _, false) => &[ModeType::ZeroPageY, ModeType::AbsoluteY],
+            (false, false, false, InstrInfoRegister::Y, _, true) => &[ModeType::AbsoluteY],
+            (false, false, false, InstrInfoRegister::S | InstrInfoRegister::SY, _, _) => &[ModeType::StackRelative],
+        };
+
+        /* Forced size could not fall back to the other modes (ex. 'JMP z:$10' is an error) */
+        let candidates: Vec<ModeType> = candidates.iter().copied().filter(|mode| match (value.is_immediate, value.size) {
+            (false, OperandSize::Byte) => operand_size(*mode) == 1,
+            (false, OperandSize::Word) => operand_size(*mode) == 2,
+            _ => true
+        }).collect();
+
+        let mode = match self.find_mode(instr, &candidates) {
+            Err(CodeGeneratorError::IllegalOpcode) if value.size != OperandSize::Auto => return Err(CodeGeneratorError::ModeNotAvailable(INSTS[instr], self.current_cpu.name())),
+            mode => mode?
+        };
+        let wide_immediate = mode.mode == ModeType::Immediate && self.is_wide_immediate(instr);
+        if result.resolved && !result.fits_byte() && !wide_immediate && matches!(mode.mode, ModeType::Immediate | ModeType::IndirectY | ModeType::DirectIndirectLongY | ModeType::StackRelative | ModeType::StackRelativeIndirectY) {
+            return Err(CodeGeneratorError::ExpectedThis("byte"));
+        }
+
+        if result.resolved && !result.fits_word() && wide_immediate {
+            return Err(CodeGeneratorError::ExpectedThis("word"));
+        }
+
+        target.push(mode.opcode);
+
+        if wide_immediate {
+            self.push_number(target, result.value as u32, ModeType::Absolute)?;
+        } else {
+            self.push_number(target, result.value as u32, mode.mode)?;
         }
+
+        self.track_register_width(instr, mode.mode, &result);
         Ok(())
     }
 
     fn generate_implied(&mut self, target: &mut Vec<u8>, position: usize) -> Result<(), CodeGeneratorError> {
-        let modes = MODES[position];
-        for search_mode in modes.iter() {
-            if search_mode.mode == ModeType::Implied {
-                target.push(search_mode.opcode);
-                break;
-            }
-        }
+        let mode = self.find_mode(position, &[ModeType::Implied, ModeType::Accumulator])?;
+        target.push(mode.opcode);
         Ok(())
     }
 
-    fn generate_branch(&mut self, target: &mut [u8], name: &str, branch_type: BranchType) -> Result<(), CodeGeneratorError> {
-        match branch_type {
-            BranchType::Generic => {
-                self.branches.insert(name.to_owned(), target.len());
-                self.local_branches.clear();
+    /* Bit branch (ex. BBR0 $10, label) has zero page address and relative branch target, block move (ex. MVN $7E, $7F) has source and destination banks */
+    fn generate_instr_pair(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, instr: usize, first: &Expr, second: &Expr) -> Result<(), CodeGeneratorError> {
+        let mode = self.find_mode(instr, &[ModeType::ZeroPageRelative, ModeType::BlockMove])?;
+        let first = self.evaluate(references, first)?;
+        if first.resolved && !(0..=0xff).contains(&first.value) {
+            return Err(CodeGeneratorError::ExpectedThis(match mode.mode {
+                ModeType::BlockMove => "bank number",
+                _ => "zero page address"
+            }));
+        }
+
+        let second = self.evaluate(references, second)?;
+        target.push(mode.opcode);
+
+        match mode.mode {
+            ModeType::BlockMove => {
+                if second.resolved && !(0..=0xff).contains(&second.value) {
+                    return Err(CodeGeneratorError::ExpectedThis("bank number"));
+                }
+
+                /* Destination bank comes first in the machine code */
+                target.push(second.value as u8);
+                target.push(first.value as u8);
             },
-            BranchType::Local => {
-                self.local_branches.insert(name.to_owned(), target.len());
-                self.build_unresolved_local_branches(target)?;
+            _ => {
+                let offset = second.value - (self.current_address(target) as i64 + 2);
+                target.push(first.value as u8);
+                target.push(offset as u8);
+
+                if second.resolved && !SHORT_BRANCH_RANGE.contains(&offset) {
+                    return Err(CodeGeneratorError::BranchOutOfRange(offset, *SHORT_BRANCH_RANGE.start(), *SHORT_BRANCH_RANGE.end()));
+                }
             }
         };
         Ok(())
     }
 
-    fn build_unresolved_relative_jump(&mut self, target: &mut [u8]) -> Result<(), CodeGeneratorError> {
-        for (branch_name, position, _) in self.unresolved_relative_jump.iter() {
-            match self.branches.get(branch_name) {
-                Some(branch_position) => target[*position] = (*branch_position as i8 - *position as i8 - 1) as u8,
-                None => return Err(CodeGeneratorError::UnresolvedBranches)
-            };
-        }
+    fn generate_branch(&mut self, target: &mut [u8], references: &HashMap<String, Vec<DirectiveValue>>, name: &str, branch_type: BranchType) -> Result<(), CodeGeneratorError> {
+        let address = self.current_address(target);
 
+        let has_branch = match branch_type {
+            BranchType::Generic => {
+                self.scope = name.to_owned();
+                self.label_offsets.insert(name.to_owned(), target.len());
+                references.contains_key(name) || self.branches.insert(name.to_owned(), address).is_some()
+            },
+            BranchType::Local => {
+                let name = self.local_name(name);
+                self.label_offsets.insert(name.clone(), target.len());
+                self.local_branches.insert(name, address).is_some()
+            },
+            BranchType::Internal => self.branches.insert(name.to_owned(), address).is_some()
+        };
+
+        if has_branch {
+            return Err(CodeGeneratorError::SymbolAlreadyDefined(name.to_owned()));
+        }
         Ok(())
     }
 
-    fn build_unresolved_local_branches(&mut self, target: &mut [u8]) -> Result<(), CodeGeneratorError> {
-        for (branch_name, position, _) in self.unresolved_local_branches.iter() {
-            if let Some(branch_position) = self.local_branches.get(branch_name) {
-                 target[*position] = (*branch_position as i8 - *position as i8 - 1) as u8;
-            };
+    fn directive_org(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+        let address = self.directive_number(references, &values[0])?;
+        self.base_offset = 0;
+
+        if !address.resolved {
+            return Ok(());
         }
 
-        Ok(())
-    }
+        if target.is_empty() {
+            self.start_point = address.value as u32;
+            return Ok(());
+        }
 
-    fn build_unresolved_jumps(&mut self, target: &mut [u8]) -> Result<(), CodeGeneratorError> {
-        for (branch_name, position, _) in self.unresolved_absolute_jumps.iter() {
-            match self.branches.get(branch_name) {
-                Some(branch_position) => {
-                    let jump_position = self.start_point + *branch_position as u16;
+        /* Next regions are placed after the previous one, the gap is filled with fillvalue */
+        let location = self.start_point as usize + target.len();
+        let address = address.value as u32;
 
-                    target[*position] = jump_position as u8;
-                    target[*position + 1] = (jump_position >> 8) as u8;
-                }
-                None => return Err(CodeGeneratorError::UnresolvedBranches)
-            };
+        if (address as usize) < location {
+            return Err(CodeGeneratorError::AddressAlreadyPassed(address));
+        }
+
+        if location < address as usize {
+            self.org_gaps.push(target.len()..target.len() + (address as usize - location));
         }
 
+        for _ in location..address as usize {
+            target.push(self.fillvalue);
+        }
         Ok(())
     }
 
-    fn directive_org(&mut self, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
-        self.start_point = values[0].get_word()?;
+    fn directive_base(&mut self, target: &[u8], references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+        let address = self.directive_number(references, &values[0])?.value;
+        self.base_offset = address - (self.start_point as i64 + target.len() as i64);
         Ok(())
     }
 
@@ -267,30 +552,36 @@ impl CodeGenerator {
         Ok(())
     }
 
-    fn directive_byte(&mut self, target: &mut Vec<u8>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+    fn directive_byte(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
         for value in values.iter() {
-            match value {
-                DirectiveValue::Byte(byte) => target.push(*byte),
-                DirectiveValue::String(string) => string.as_bytes().iter().for_each(|byte| target.push(*byte)),
-                _ => return Err(CodeGeneratorError::ExpectedThis("byte or &String"))
+            let expr = match value {
+                DirectiveValue::String(string) => {
+                    string.as_bytes().iter().for_each(|byte| target.push(*byte));
+                    continue;
+                },
+                value => value.as_expr().ok_or(CodeGeneratorError::ExpectedThis("byte or &String"))?
             };
+
+            let number = self.evaluate(references, &expr)?;
+            if !number.fits_byte() {
+                return Err(CodeGeneratorError::ExpectedThis("byte or &String"));
+            }
+
+            target.push(number.value as u8);
         }
         Ok(())
     }
 
-    fn directive_word(&mut self, target: &mut Vec<u8>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+    fn directive_word(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
         for value in values.iter() {
-            match value {
-                DirectiveValue::Byte(word) => {
-                    target.push(*word);
-                    target.push(0x00);
-                },
-                DirectiveValue::Word(word) => {
-                    target.push(*word as u8);
-                    target.push((*word >> 8) as u8);
-                },
-                _ => return Err(CodeGeneratorError::ExpectedThis("word"))
+            let expr = value.as_expr().ok_or(CodeGeneratorError::ExpectedThis("word"))?;
+            let number = self.evaluate(references, &expr)?;
+            if !number.fits_word() {
+                return Err(CodeGeneratorError::ExpectedThis("word"));
             }
+
+            target.push(number.value as u8);
+            target.push((number.value >> 8) as u8);
         }
         Ok(())
     }
@@ -312,68 +603,120 @@ impl CodeGenerator {
         Ok(())
     }
 
-    fn directive_warning(&mut self, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+    fn directive_warning(&mut self, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
         let mut message = String::new();
 
         for value in values.iter() {
             match value {
                 DirectiveValue::String(string) => message += &string[..],
-                DirectiveValue::Word(word) => message += &format!("0x{:02X}", word),
-                DirectiveValue::Byte(byte) => message += &format!("0x{:02X}", byte),
-                _ => return Err(CodeGeneratorError::ExpectedThis("string"))
+                value => message += &format!("0x{:02X}", self.directive_number(references, value)?.value)
             };
         }
-        
-        if !self.silent {
-            warn!("{}", message);
-        }
+
+        self.warnings.push(message);
         Ok(())
     }
 
-    fn directive_fail(&mut self, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+    fn directive_fail(&mut self, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
         let mut message = String::new();
 
         for value in values.iter() {
             match value {
                 DirectiveValue::String(string) => message += &string[..],
-                DirectiveValue::Word(word) => message += &format!("0x{:02X}", word),
-                DirectiveValue::Byte(byte) => message += &format!("0x{:02X}", byte),
-                _ => return Err(CodeGeneratorError::ExpectedThis("string"))
+                value => {
+                    let number = self.directive_number(references, value)?;
+
+                    /* Wait until the message is complete */
+                    if !number.resolved {
+                        return Ok(());
+                    }
+                    message += &format!("0x{:02X}", number.value)
+                }
             };
         }
         Err(CodeGeneratorError::ProgramFailed(message))
     }
 
-    fn directive_pad(&mut self, target: &mut Vec<u8>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
-        let address = match &values[0] {
-            DirectiveValue::Word(address) => *address,
-            _ => return Err(CodeGeneratorError::ExpectedThis("word"))
-        };
+    fn directive_pad(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+        let address = self.directive_number(references, &values[0])?;
+
+        if !address.resolved {
+            return Ok(());
+        }
+
+        if !(0..=0xff_ffff).contains(&address.value) {
+            return Err(CodeGeneratorError::ExpectedThis("address"));
+        }
+
+        let address = address.value as usize;
+        let location = self.current_address(target);
 
-        for _ in 0..(address as usize-target.len()) {
+        if address < location {
+            return Err(CodeGeneratorError::AddressAlreadyPassed(address as u32));
+        }
+
+        for _ in location..address {
             target.push(self.fillvalue);
         }
 
         Ok(())
     }
 
-    fn directive_fillvalue(&mut self, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
-        self.fillvalue = values[0].get_byte()?;
+    fn directive_fillvalue(&mut self, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+        self.fillvalue = self.directive_number(references, &values[0])?.value as u8;
         Ok(())
     }
 
-    fn directive_define_storage_byte(&self, target: &mut Vec<u8>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+    fn directive_cpu(&mut self, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+        let name = match &values[0] {
+            DirectiveValue::String(name) => name,
+            _ => return Err(CodeGeneratorError::StringExpected)
+        };
+
+        self.current_cpu = CpuType::from_name(name).ok_or_else(|| CodeGeneratorError::UnsupportedCpu(name.clone()))?;
+        Ok(())
+    }
+
+    /* '.longbranch on' or '.longbranch off', numbers are also accepted */
+    fn directive_longbranch(&mut self, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+        self.current_relax_branches = match &values[0] {
+            DirectiveValue::Reference(name) if name.eq_ignore_ascii_case("on") => true,
+            DirectiveValue::Reference(name) if name.eq_ignore_ascii_case("off") => false,
+            value => self.directive_number(references, value)?.value != 0
+        };
+        Ok(())
+    }
+
+    fn directive_ines(&mut self, references: &HashMap<String, Vec<DirectiveValue>>, option: DirectiveEnum, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+        let value = self.directive_number(references, &values[0])?.value as u32;
+        match option {
+            DirectiveEnum::Inesprg => self.ines.prg_banks = Some(value),
+            DirectiveEnum::Ineschr => self.ines.chr_banks = value,
+            DirectiveEnum::Inesmap => self.ines.mapper = value,
+            DirectiveEnum::Inesmir => self.ines.mirroring = value,
+            DirectiveEnum::Nes2sub => self.ines.submapper = value,
+            DirectiveEnum::Nes2prgram => self.ines.prg_ram = value,
+            DirectiveEnum::Nes2bram => self.ines.prg_nvram = value,
+            DirectiveEnum::Nes2chrram => self.ines.chr_ram = value,
+            DirectiveEnum::Nes2chrbram => self.ines.chr_nvram = value,
+            DirectiveEnum::Nes2tv => self.ines.timing = value,
+            _ => return Err(CodeGeneratorError::InternalError)
+        };
+
+        /* NES 2.0 fields need NES 2.0 header */
+        self.ines.nes2 |= !matches!(option, DirectiveEnum::Inesprg | DirectiveEnum::Ineschr | DirectiveEnum::Inesmap | DirectiveEnum::Inesmir);
+        Ok(())
+    }
+
+    fn directive_define_storage_byte(&self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
         let mut filler = 0x00;
         let times = match &values[0] {
-            DirectiveValue::Byte(byte) => *byte,
-            DirectiveValue::Word(word) => *word as u8,
-            _ => return Err(CodeGeneratorError::UnsupportedDirectiveValue)
+            DirectiveValue::String(_) => return Err(CodeGeneratorError::UnsupportedDirectiveValue),
+            value => self.directive_number(references, value)?.value as u16
         };
 
         if values.len() > 1 {
-            if let Ok(new_filler) = values[1].get_byte() {
-                filler = new_filler;
-            }
+            filler = self.directive_number(references, &values[1])?.value as u8;
         }
 
         for _ in 0..times {
@@ -383,18 +726,15 @@ impl CodeGenerator {
         Ok(())
     }
 
-    fn directive_define_storage_word(&self, target: &mut Vec<u8>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+    fn directive_define_storage_word(&self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
         let mut filler: u16 = 0x00;
         let times = match &values[0] {
-            DirectiveValue::Byte(byte) => *byte as u16,
-            DirectiveValue::Word(word) => *word,
-            _ => return Err(CodeGeneratorError::UnsupportedDirectiveValue)
+            DirectiveValue::String(_) => return Err(CodeGeneratorError::UnsupportedDirectiveValue),
+            value => self.directive_number(references, value)?.value as u16
         };
 
         if values.len() > 1 {
-            if let Ok(new_filler) = values[1].get_word() {
-                filler = new_filler;
-            }
+            filler = self.directive_number(references, &values[1])?.value as u16;
         }
 
         for _ in 0..times {
@@ -405,62 +745,178 @@ impl CodeGenerator {
         Ok(())
     }
 
-    fn generate_directive(&mut self, target: &mut Vec<u8>, option: DirectiveEnum, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
+    fn generate_directive(&mut self, target: &mut Vec<u8>, references: &HashMap<String, Vec<DirectiveValue>>, option: DirectiveEnum, values: &[DirectiveValue]) -> Result<(), CodeGeneratorError> {
         match option {
-            DirectiveEnum::Org => self.directive_org(values)?,
+            DirectiveEnum::Org => self.directive_org(target, references, values)?,
+            DirectiveEnum::Base => self.directive_base(target, references, values)?,
+            DirectiveEnum::Rend => self.base_offset = 0,
             DirectiveEnum::Incbin => self.directive_incbin(target, values)?,
-            DirectiveEnum::Byte => self.directive_byte(target, values)?,
-            DirectiveEnum::Word => self.directive_word(target, values)?,
+            DirectiveEnum::Byte => self.directive_byte(target, references, values)?,
+            DirectiveEnum::Word => self.directive_word(target, references, values)?,
             DirectiveEnum::Ascii => self.directive_ascii(target, values, false)?,
             DirectiveEnum::Asciiz => self.directive_ascii(target, values, true)?,
-            DirectiveEnum::Warning => self.directive_warning(values)?,
-            DirectiveEnum::Fail => self.directive_fail(values)?,
-            DirectiveEnum::Include => (),
-            DirectiveEnum::Pad => self.directive_pad(target, values)?,
-            DirectiveEnum::Fillvalue => self.directive_fillvalue(values)?,
-            DirectiveEnum::Dsb => self.directive_define_storage_byte(target, values)?,
-            DirectiveEnum::Dsw => self.directive_define_storage_word(target, values)?,
+            DirectiveEnum::Warning => self.directive_warning(references, values)?,
+            DirectiveEnum::Fail => self.directive_fail(references, values)?,
+            DirectiveEnum::Include | DirectiveEnum::Macro | DirectiveEnum::Endm | DirectiveEnum::Rept | DirectiveEnum::Endr => (),
+            DirectiveEnum::If | DirectiveEnum::Ifdef | DirectiveEnum::Ifndef | DirectiveEnum::Elseif | DirectiveEnum::Else | DirectiveEnum::Endif => (),
+            DirectiveEnum::Pad => self.directive_pad(target, references, values)?,
+            DirectiveEnum::Fillvalue => self.directive_fillvalue(references, values)?,
+            DirectiveEnum::Cpu => self.directive_cpu(values)?,
+            DirectiveEnum::Inesprg | DirectiveEnum::Ineschr | DirectiveEnum::Inesmap | DirectiveEnum::Inesmir => self.directive_ines(references, option, values)?,
+            DirectiveEnum::Nes2sub | DirectiveEnum::Nes2prgram | DirectiveEnum::Nes2bram | DirectiveEnum::Nes2chrram | DirectiveEnum::Nes2chrbram | DirectiveEnum::Nes2tv => self.directive_ines(references, option, values)?,
+            DirectiveEnum::Chr => self.chr_start = Some(target.len()),
+            DirectiveEnum::A8 => self.accumulator_16 = false,
+            DirectiveEnum::A16 => self.accumulator_16 = true,
+            DirectiveEnum::I8 => self.index_16 = false,
+            DirectiveEnum::I16 => self.index_16 = true,
+            DirectiveEnum::Dsb => self.directive_define_storage_byte(target, references, values)?,
+            DirectiveEnum::Dsw => self.directive_define_storage_word(target, references, values)?,
+            DirectiveEnum::Longbranch => self.directive_longbranch(references, values)?,
         };
         Ok(())
     }
 
-    fn inner_generate(&mut self, context: &mut Context) -> Result<(), CodeGeneratorError> {
-        self.size = context.asts.borrow().len();
+    fn generate_pass(&mut self, context: &mut Context) -> Result<(), CodeGeneratorError> {
         let asts = context.asts.borrow();
-        
+        let references = context.references.borrow();
+
+        self.index = 0;
+        self.start_point = Default::default();
+        self.base_offset = 0;
+        self.fillvalue = 0x00;
+        self.current_cpu = self.cpu;
+        self.current_relax_branches = self.relax_branches;
+        self.accumulator_16 = false;
+        self.index_16 = false;
+        self.ines = Default::default();
+        self.chr_start = None;
+        self.org_gaps.clear();
+        self.label_offsets.clear();
+        self.ast_ranges.clear();
+        self.scope = Default::default();
+        self.warnings.clear();
+        self.errors.clear();
+        self.unresolved.replace(Vec::new());
+        context.target.clear();
+
         while self.size > self.index {
             let ast_index = self.eat()?;
             let ast = asts.get(ast_index).map(|item| &item.ast);
-
-            match ast {
-                Some(Ast::InstrImplied(position)) => self.generate_implied(&mut context.target, *position)?,
-                Some(Ast::Instr(position, value)) => self.generate_instr(&mut context.target, ast_index, *position, value)?,
-                Some(Ast::Branch(name, branch_type)) => self.generate_branch(&mut context.target, name, *branch_type)?,
-                Some(Ast::Directive(option, values)) => self.generate_directive(&mut context.target, *option, values)?,
+            self.program_counter = self.current_address(&context.target);
+            let mut address = self.program_counter;
+            let mut start = context.target.len();
+
+            let result = match ast {
+                Some(Ast::InstrImplied(position)) => self.generate_implied(&mut context.target, *position),
+                Some(Ast::Instr(position, value)) => self.generate_instr(&mut context.target, &references, *position, value),
+                Some(Ast::InstrPair(position, first, second)) => self.generate_instr_pair(&mut context.target, &references, *position, first, second),
+                Some(Ast::Branch(name, branch_type)) => self.generate_branch(&mut context.target, &references, name, *branch_type),
+                Some(Ast::Directive(option, values)) => self.generate_directive(&mut context.target, &references, *option, values),
+                Some(Ast::Include(_)) => Ok(()),
                 None => return Err(CodeGeneratorError::InternalError)
             };
-        }
 
-        self.build_unresolved_relative_jump(&mut context.target)?;
-        self.build_unresolved_jumps(&mut context.target)?;
+            /* Generation continues after an error, errors of the last pass are reported */
+            if let Err(error) = result {
+                self.errors.push((error, ast_index));
+            }
+
+            /* .org gap is not a part of the line, the line starts from the new address */
+            if let Some(Ast::Directive(DirectiveEnum::Org, _)) = ast {
+                address = self.current_address(&context.target);
+                start = context.target.len();
+            }
+
+            /* Address and generated bytes of every ast, used by the listing */
+            self.ast_ranges.push((address, start..context.target.len()));
+        }
         Ok(())
     }
 
+    fn inner_generate(&mut self, context: &mut Context) -> Result<(), CodeGeneratorError> {
+        self.size = context.asts.borrow().len();
+        self.relaxed_branches.clear();
+        self.absolute_operands.clear();
+
+        for _ in 0..MAX_PASSES {
+            self.generate_pass(context)?;
+
+            let stable = self.branches == self.previous_branches && self.local_branches == self.previous_local_branches;
+
+            if stable {
+                /* Unresolved symbols are generated as zero and reported with the other errors */
+                let mut errors = std::mem::take(&mut self.errors);
+                errors.extend(self.unresolved.take().into_iter().map(|(name, ast_index)| (CodeGeneratorError::UnresolvedReference(name), ast_index)));
+                errors.sort_by_key(|(_, ast_index)| *ast_index);
+
+                if let Some(error) = self.report_errors(context, errors) {
+                    return Err(error);
+                }
+
+                if !self.silent {
+                    self.warnings.iter().for_each(|message| warn!("{}", message));
+                }
+                return Ok(());
+            }
+
+            self.previous_branches = std::mem::take(&mut self.branches);
+            self.previous_local_branches = std::mem::take(&mut self.local_branches);
+        }
+
+        let errors = std::mem::take(&mut self.errors);
+        match self.report_errors(context, errors) {
+            Some(error) => Err(error),
+            None => Err(CodeGeneratorError::NotConverged(MAX_PASSES))
+        }
+    }
+
+    /* Prints the errors up to the limit, returns the first one */
+    fn report_errors(&mut self, context: &Context, errors: Vec<(CodeGeneratorError, usize)>) -> Option<CodeGeneratorError> {
+        self.total_errors = errors.len();
+        let printable = context.max_errors.saturating_sub(context.total_errors.get());
+        context.total_errors.set(context.total_errors.get() + errors.len());
+
+        if !context.silent {
+            let asts = context.asts.borrow();
+            let code_files = context.code_files.borrow();
+
+            for (error, ast_index) in errors.iter().take(printable) {
+                if let Some(ast) = asts.get(*ast_index) {
+                    print_code_error(&code_files, ast.file_id, error, ast.line, ast.column, ast.end);
+                    print_macro_calls(&code_files, &ast.macro_call);
+                }
+            }
+        }
+
+        errors.into_iter().next().map(|(error, _)| error)
+    }
+
+    #[cfg(test)]
     pub fn generate(&mut self, context: Context) -> Result<Context, CodeGeneratorError> {
         let mut context = context;
-        
-        match self.inner_generate(&mut context) {
-            Ok(_) => Ok(context),
-            Err(error) => {
-                let asts = context.asts.borrow();
-                let ast = &asts[self.index - 1];
-                if !context.silent {
-                    let code_file = &context.code_files.borrow()[0];
-                    print_error(&code_file.data, &error, ast.line, ast.column, ast.end);
+        self.generate_in_place(&mut context)?;
+        Ok(context)
+    }
+
+    /* Context is kept after an error, the errors of the previous stages are counted in the error limit */
+    pub fn generate_in_place(&mut self, context: &mut Context) -> Result<(), CodeGeneratorError> {
+        self.inner_generate(context)
+    }
+
+    /* Values of the number consts after the generation, consts with strings or many values are skipped */
+    pub fn constants(&self, context: &Context) -> Vec<(String, i64)> {
+        let references = context.references.borrow();
+        let mut constants = Vec::new();
+
+        for (name, values) in references.iter() {
+            if let [value] = &values[..] {
+                if let Some(value) = value.as_expr().and_then(|expr| self.evaluate(&references, &expr).ok()).filter(|value| value.resolved) {
+                    constants.push((name.clone(), value.value));
                 }
-                Err(error)
             }
         }
+
+        constants
     }
 
     pub fn dump(&self, context: &Context) {
@@ -476,7 +932,7 @@ impl CodeGenerator {
             
             if index > 1 && (index+1) % total_byte_per_row == 0 && index != total_bytes-1 {
                 println!();
-                print!("{:04X}: ", position + 1 + (index as u16));
+                print!("{:04X}: ", position + 1 + (index as u32));
         
             }
         }
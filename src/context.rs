@@ -1,6 +1,9 @@
-use std::{cell::RefCell, collections::HashMap, path::PathBuf};
+use std::{cell::{Cell, RefCell}, collections::HashMap, path::PathBuf};
 
-use crate::{ast::{Ast, AstInfo}, directive::DirectiveValue, parser::TokenInfo};
+use crate::{ast::{Ast, AstInfo}, directive::DirectiveValue, opcode::CpuType, parser::TokenInfo};
+
+/* Compilation continues after errors, the stages stop after this many errors in total */
+pub const DEFAULT_MAX_ERRORS: usize = 20;
 
 #[derive(Debug)]
 pub struct Context {
@@ -11,14 +14,24 @@ pub struct Context {
     pub files: RefCell<Vec<PathBuf>>,
     pub work_directory: PathBuf,
     pub silent: bool,
-    pub code_files: RefCell<Vec<CodeFile>>
+    pub max_errors: usize,
+
+    /* Errors of all stages, only the first max_errors are printed */
+    pub total_errors: Cell<usize>,
+    pub code_files: RefCell<Vec<CodeFile>>,
+
+    /* CPU at the beginning of the file, only its mnemonics are tokenized as instructions */
+    pub cpu: CpuType
 }
 
 #[derive(Debug)]
 pub struct CodeFile {
     pub path: PathBuf,
     pub includes: Vec<PathBuf>,
-    pub data: Vec<u8>
+    pub data: Vec<u8>,
+
+    /* File id and line of the .include directive */
+    pub included_from: Option<(usize, usize)>
 }
 
 impl Context {
@@ -26,9 +39,11 @@ impl Context {
         let token_info = &self.tokens.borrow()[token_index];
 
         let info = AstInfo {
+            file_id: token_info.file_id,
             line: token_info.line,
             column: token_info.column,
             end: token_info.end,
+            macro_call: token_info.macro_call.clone(),
             ast
         };
 
@@ -50,10 +65,21 @@ impl Context {
         };
 
         files.push(full_file_path.clone());
-        code_files.push(CodeFile { path: full_file_path.clone(), includes: Vec::new(), data: Vec::new() });
+        code_files.push(CodeFile { path: full_file_path.clone(), includes: Vec::new(), data: Vec::new(), included_from: None });
         full_file_path
     }
 
+    /* Counts the error, returns true if it could be printed */
+    pub fn add_error(&self) -> bool {
+        self.total_errors.set(self.total_errors.get() + 1);
+        self.total_errors.get() <= self.max_errors
+    }
+
+    /* Zero limit prints no error, the stages still stop at the first one */
+    pub fn error_limit_reached(&self) -> bool {
+        self.total_errors.get() >= self.max_errors.max(1)
+    }
+
     pub fn last_file_id(&self) -> usize {
         self.files.borrow().len() - 1
     }
@@ -75,7 +101,10 @@ impl Default for Context {
             references: Default::default(),
             files: Default::default(),
             silent: false,
-            code_files: Default::default()
+            max_errors: DEFAULT_MAX_ERRORS,
+            total_errors: Cell::new(0),
+            code_files: Default::default(),
+            cpu: Default::default()
         }
     }
 }
@@ -1,10 +1,12 @@
 use strum_macros::EnumDiscriminants;
 
-use crate::code_gen::CodeGeneratorError;
+use crate::{ast::Expr, code_gen::CodeGeneratorError};
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum DirectiveEnum {
     Org,
+    Base,
+    Rend,
     Incbin,
     Byte,
     Word,
@@ -15,8 +17,35 @@ pub enum DirectiveEnum {
     Include,
     Pad,
     Fillvalue,
+    Cpu,
+    A8,
+    A16,
+    I8,
+    I16,
+    Inesprg,
+    Ineschr,
+    Inesmap,
+    Inesmir,
+    Nes2sub,
+    Nes2prgram,
+    Nes2bram,
+    Nes2chrram,
+    Nes2chrbram,
+    Nes2tv,
+    Chr,
     Dsb,
-    Dsw
+    Dsw,
+    Longbranch,
+    Macro,
+    Endm,
+    If,
+    Ifdef,
+    Ifndef,
+    Elseif,
+    Else,
+    Endif,
+    Rept,
+    Endr
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -27,6 +56,18 @@ pub enum DirectiveValue {
     Word(u16),
     String(String),
     Reference(String),
+    Expression(Expr)
+}
+
+impl From<Expr> for DirectiveValue {
+    fn from(expr: Expr) -> Self {
+        match expr {
+            Expr::Byte(byte) => DirectiveValue::Byte(byte),
+            Expr::Word(word) => DirectiveValue::Word(word),
+            Expr::Reference(name) => DirectiveValue::Reference(name),
+            expr => DirectiveValue::Expression(expr)
+        }
+    }
 }
 
 impl DirectiveValue {
@@ -38,6 +79,16 @@ impl DirectiveValue {
         }
     }
 
+    pub fn as_expr(&self) -> Option<Expr> {
+        match self {
+            DirectiveValue::Byte(byte) => Some(Expr::Byte(*byte)),
+            DirectiveValue::Word(word) => Some(Expr::Word(*word)),
+            DirectiveValue::Reference(name) => Some(Expr::Reference(name.clone())),
+            DirectiveValue::Expression(expr) => Some(expr.clone()),
+            DirectiveValue::String(_) => None
+        }
+    }
+
     pub fn get_byte(&self) -> Result<u8, CodeGeneratorError> {
         
         match self {
@@ -62,20 +113,60 @@ pub struct DirectiveInfo {
     pub values: &'static [DirectiveType]
 }
 
+pub fn find_directive(name: &str) -> Option<DirectiveEnum> {
+    let name = name.to_uppercase();
+    SYSTEM_DIRECTIVES.iter().find(|item| item.name == &name[..]).map(|item| item.directive)
+}
+
 pub const SYSTEM_DIRECTIVES: &[DirectiveInfo] = &[
     DirectiveInfo { name: "BYTE",      directive: DirectiveEnum::Byte,      size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::String] },
     DirectiveInfo { name: "DB",        directive: DirectiveEnum::Byte,      size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::String] },
     DirectiveInfo { name: "WORD",      directive: DirectiveEnum::Word,      size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::Word] },
     DirectiveInfo { name: "DW",        directive: DirectiveEnum::Word,      size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::Word] },
-    DirectiveInfo { name: "ORG",       directive: DirectiveEnum::Org,       size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Word] },
+    DirectiveInfo { name: "ORG",       directive: DirectiveEnum::Org,       size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "BASE",      directive: DirectiveEnum::Base,      size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "RORG",      directive: DirectiveEnum::Base,      size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "REND",      directive: DirectiveEnum::Rend,      size: DirectiveVariableSize::None,        values: &[] },
     DirectiveInfo { name: "INCBIN",    directive: DirectiveEnum::Incbin,    size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::String] },
     DirectiveInfo { name: "ASCII",     directive: DirectiveEnum::Ascii,     size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::String] },
     DirectiveInfo { name: "ASCIIZ",    directive: DirectiveEnum::Asciiz,    size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::String] },
     DirectiveInfo { name: "WARNING",   directive: DirectiveEnum::Warning,   size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::String, DirectiveType::Word, DirectiveType::Byte] },
     DirectiveInfo { name: "FAIL",      directive: DirectiveEnum::Fail   ,   size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::String, DirectiveType::Word, DirectiveType::Byte] },
     DirectiveInfo { name: "INCLUDE",   directive: DirectiveEnum::Include,   size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::String] },
-    DirectiveInfo { name: "PAD",       directive: DirectiveEnum::Pad,       size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Word] },
+    DirectiveInfo { name: "PAD",       directive: DirectiveEnum::Pad,       size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
     DirectiveInfo { name: "FILLVALUE", directive: DirectiveEnum::Fillvalue, size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte] },
+    DirectiveInfo { name: "CPU",       directive: DirectiveEnum::Cpu,       size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::String] },
+    DirectiveInfo { name: "SETCPU",    directive: DirectiveEnum::Cpu,       size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::String] },
+    DirectiveInfo { name: "A8",        directive: DirectiveEnum::A8,        size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "A16",       directive: DirectiveEnum::A16,       size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "I8",        directive: DirectiveEnum::I8,        size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "I16",       directive: DirectiveEnum::I16,       size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "INESPRG",    directive: DirectiveEnum::Inesprg,     size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "INESCHR",    directive: DirectiveEnum::Ineschr,     size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "INESMAP",    directive: DirectiveEnum::Inesmap,     size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "INESMIR",    directive: DirectiveEnum::Inesmir,     size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "NES2SUB",    directive: DirectiveEnum::Nes2sub,     size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "NES2PRGRAM", directive: DirectiveEnum::Nes2prgram,  size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "NES2BRAM",   directive: DirectiveEnum::Nes2bram,    size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "NES2CHRRAM", directive: DirectiveEnum::Nes2chrram,  size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "NES2CHRBRAM", directive: DirectiveEnum::Nes2chrbram, size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "NES2TV",     directive: DirectiveEnum::Nes2tv,      size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "CHR",        directive: DirectiveEnum::Chr,         size: DirectiveVariableSize::None,        values: &[] },
     DirectiveInfo { name: "DSB",       directive: DirectiveEnum::Dsb,       size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::Word] },
     DirectiveInfo { name: "DSW",       directive: DirectiveEnum::Dsw,       size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "LONGBRANCH", directive: DirectiveEnum::Longbranch, size: DirectiveVariableSize::Length(1), values: &[DirectiveType::Byte, DirectiveType::Reference] },
+    DirectiveInfo { name: "MACRO",     directive: DirectiveEnum::Macro,     size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "ENDM",      directive: DirectiveEnum::Endm,      size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "ENDMACRO",  directive: DirectiveEnum::Endm,      size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "IF",        directive: DirectiveEnum::If,        size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "IFDEF",     directive: DirectiveEnum::Ifdef,     size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Reference] },
+    DirectiveInfo { name: "IFNDEF",    directive: DirectiveEnum::Ifndef,    size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Reference] },
+    DirectiveInfo { name: "ELSEIF",    directive: DirectiveEnum::Elseif,    size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "ELIF",      directive: DirectiveEnum::Elseif,    size: DirectiveVariableSize::Length(1),   values: &[DirectiveType::Byte, DirectiveType::Word] },
+    DirectiveInfo { name: "ELSE",      directive: DirectiveEnum::Else,      size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "ENDIF",     directive: DirectiveEnum::Endif,     size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "REPT",      directive: DirectiveEnum::Rept,      size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::Word, DirectiveType::Reference] },
+    DirectiveInfo { name: "REPEAT",    directive: DirectiveEnum::Rept,      size: DirectiveVariableSize::Min(1),      values: &[DirectiveType::Byte, DirectiveType::Word, DirectiveType::Reference] },
+    DirectiveInfo { name: "ENDR",      directive: DirectiveEnum::Endr,      size: DirectiveVariableSize::None,        values: &[] },
+    DirectiveInfo { name: "ENDREP",    directive: DirectiveEnum::Endr,      size: DirectiveVariableSize::None,        values: &[] },
 ];
\ No newline at end of file
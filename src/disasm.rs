@@ -0,0 +1,267 @@
+use std::{collections::HashSet, fmt::Write, ops::{Range, RangeInclusive}};
+
+use crate::opcode::{decode_opcode, is_accumulator_width, is_index_width, operand_size, CpuType, ModeInfo, ModeType, INSTS};
+
+const DATA_BYTES_PER_LINE: usize = 8;
+
+#[derive(Debug, Default, Clone)]
+pub struct DisasmOptions {
+    pub start: u32,
+    pub cpu: CpuType,
+
+    /* Only code ranges are disassembled if there is any, data ranges are always written as bytes */
+    pub code: Vec<RangeInclusive<u32>>,
+    pub data: Vec<RangeInclusive<u32>>
+}
+
+#[derive(Debug)]
+enum Line {
+    Instr(usize, &'static ModeInfo, Range<usize>),
+    Data(Range<usize>)
+}
+
+struct Disassembler<'a> {
+    data: &'a [u8],
+    options: &'a DisasmOptions,
+    accumulator_16: bool,
+    index_16: bool
+}
+
+fn hex(value: u32) -> String {
+    match value {
+        0..=0xff => format!("${:02X}", value),
+        0x100..=0xffff => format!("${:04X}", value),
+        _ => format!("${:06X}", value)
+    }
+}
+
+/* Words are written with 4 digits and long addresses with 6 digits, so the assembler selects the same operand size */
+fn word(value: u32) -> String {
+    format!("${:04X}", value)
+}
+
+/* Zero page address is forced to absolute with 'a:', otherwise the assembler selects the zero page mode */
+fn absolute(value: u32) -> String {
+    match value {
+        0..=0xff => format!("a:{}", word(value)),
+        _ => word(value)
+    }
+}
+
+fn long(value: u32) -> String {
+    format!("${:06X}", value)
+}
+
+fn label_name(address: u32) -> String {
+    match address > 0xffff {
+        true => format!("L_{:06X}", address),
+        false => format!("L_{:04X}", address)
+    }
+}
+
+impl<'a> Disassembler<'a> {
+    fn address(&self, position: usize) -> u32 {
+        self.options.start + position as u32
+    }
+
+    fn is_code(&self, position: usize) -> bool {
+        let address = self.address(position);
+        let in_code = self.options.code.is_empty() || self.options.code.iter().any(|range| range.contains(&address));
+        in_code && !self.options.data.iter().any(|range| range.contains(&address))
+    }
+
+    /* 65816 immediate operand size depends on the register sizes, REP and SEP are tracked like the assembler */
+    fn instr_size(&self, instr: usize, mode: ModeType) -> usize {
+        let wide = self.options.cpu == CpuType::Wdc65816 && mode == ModeType::Immediate &&
+            ((self.accumulator_16 && is_accumulator_width(instr)) || (self.index_16 && is_index_width(instr)));
+        1 + operand_size(mode) + wide as usize
+    }
+
+    fn track_register_width(&mut self, instr: usize, mode: ModeType, value: u8) {
+        let set = match (INSTS[instr], mode) {
+            ("REP", ModeType::Immediate) => false,
+            ("SEP", ModeType::Immediate) => true,
+            _ => return
+        };
+
+        if value & 0x20 != 0 {
+            self.accumulator_16 = !set;
+        }
+
+        if value & 0x10 != 0 {
+            self.index_16 = !set;
+        }
+    }
+
+    fn decode(&mut self, position: usize) -> Option<Line> {
+        if !self.is_code(position) {
+            return None;
+        }
+
+        let (instr, info) = decode_opcode(self.data[position], self.options.cpu)?;
+        let size = self.instr_size(instr, info.mode);
+        let end = position + size;
+
+        /* Instruction could not continue in data or after the end of the file */
+        if end > self.data.len() || !(position..end).all(|index| self.is_code(index)) {
+            return None;
+        }
+
+        if size > 1 {
+            self.track_register_width(instr, info.mode, self.data[position + 1]);
+        }
+        Some(Line::Instr(instr, info, position..end))
+    }
+
+    fn lines(&mut self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut position = 0;
+
+        while position < self.data.len() {
+            match self.decode(position) {
+                Some(Line::Instr(instr, info, range)) => {
+                    position = range.end;
+                    lines.push(Line::Instr(instr, info, range));
+                },
+                _ => {
+                    match lines.last_mut() {
+                        Some(Line::Data(range)) if range.len() < DATA_BYTES_PER_LINE && range.end == position => range.end += 1,
+                        _ => lines.push(Line::Data(position..position + 1))
+                    };
+                    position += 1;
+                }
+            };
+        }
+
+        lines
+    }
+
+    fn operand(&self, range: &Range<usize>) -> u32 {
+        self.data[range.start + 1..range.end].iter().rev().fold(0, |value, byte| (value << 8) | *byte as u32)
+    }
+
+    /* Branches wrap around in the current bank */
+    fn relative_target(&self, range: &Range<usize>, offset: i32) -> u32 {
+        let address = self.address(range.end);
+        (address & 0xff0000) | ((address as i64 + offset as i64) as u32 & 0xffff)
+    }
+
+    /* Jump and branch targets, absolute jumps are in the current bank */
+    fn target(&self, instr: usize, info: &ModeInfo, range: &Range<usize>) -> Option<u32> {
+        match info.mode {
+            ModeType::Relative => Some(self.relative_target(range, self.data[range.start + 1] as i8 as i32)),
+            ModeType::RelativeLong => Some(self.relative_target(range, self.operand(range) as u16 as i16 as i32)),
+            ModeType::ZeroPageRelative => Some(self.relative_target(range, self.data[range.start + 2] as i8 as i32)),
+            ModeType::Absolute if ["JMP", "JSR"].contains(&INSTS[instr]) && self.address(range.start) <= 0xffff => Some(self.operand(range)),
+            _ => None
+        }
+    }
+
+    /* Branch target without label is written relative to the program counter (ex. '*-2') */
+    fn branch_target(&self, instr: usize, info: &ModeInfo, range: &Range<usize>, labels: &HashSet<u32>) -> String {
+        let offset = match info.mode {
+            ModeType::RelativeLong => self.operand(range) as u16 as i16 as i64,
+            _ => self.data[range.end - 1] as i8 as i64
+        };
+
+        match self.target(instr, info, range) {
+            Some(target) if labels.contains(&target) => label_name(target),
+            _ => match offset + range.len() as i64 {
+                0 => "*".to_owned(),
+                delta if delta > 0 => format!("*+{}", delta),
+                delta => format!("*-{}", -delta)
+            }
+        }
+    }
+
+    fn format_instr(&self, instr: usize, info: &ModeInfo, range: &Range<usize>, labels: &HashSet<u32>) -> String {
+        let value = self.operand(range);
+        let name = |address: u32| match labels.contains(&address) {
+            true => label_name(address),
+            false => absolute(address)
+        };
+
+        let operand = match info.mode {
+            ModeType::Implied | ModeType::Accumulator => String::new(),
+            ModeType::Immediate => match range.len() {
+                3 => format!("#{}", word(value)),
+                _ => format!("#{}", hex(value))
+            },
+            ModeType::ZeroPage => hex(value),
+            ModeType::ZeroPageX => format!("{},X", hex(value)),
+            ModeType::ZeroPageY => format!("{},Y", hex(value)),
+            ModeType::Absolute => match self.target(instr, info, range) {
+                Some(target) => name(target),
+                None => absolute(value)
+            },
+            ModeType::AbsoluteX => format!("{},X", absolute(value)),
+            ModeType::AbsoluteY => format!("{},Y", absolute(value)),
+            ModeType::IndirectX => format!("({},X)", hex(value)),
+            ModeType::IndirectY => format!("({}),Y", hex(value)),
+            ModeType::Indirect => format!("({})", absolute(value)),
+            ModeType::ZeroPageIndirect => format!("({})", hex(value)),
+            ModeType::AbsoluteIndirectX => format!("({},X)", absolute(value)),
+            ModeType::AbsoluteLong => long(value),
+            ModeType::AbsoluteLongX => format!("{},X", long(value)),
+            ModeType::DirectIndirectLong => format!("[{}]", hex(value)),
+            ModeType::DirectIndirectLongY => format!("[{}],Y", hex(value)),
+            ModeType::AbsoluteIndirectLong => format!("[{}]", absolute(value)),
+            ModeType::StackRelative => format!("{},S", hex(value)),
+            ModeType::StackRelativeIndirectY => format!("({},S),Y", hex(value)),
+            ModeType::Relative | ModeType::RelativeLong => self.branch_target(instr, info, range, labels),
+            ModeType::ZeroPageRelative => format!("{}, {}", hex(self.data[range.start + 1] as u32), self.branch_target(instr, info, range, labels)),
+
+            /* Machine code has the destination bank first */
+            ModeType::BlockMove => format!("{}, {}", hex(self.data[range.start + 2] as u32), hex(self.data[range.start + 1] as u32))
+        };
+
+        match operand.is_empty() {
+            true => INSTS[instr].to_owned(),
+            false => format!("{} {}", INSTS[instr], operand)
+        }
+    }
+}
+
+/* Re-assemblable source, branch and jump targets get 'L_xxxx' labels if they are at the beginning of a line */
+pub fn disassemble(data: &[u8], options: &DisasmOptions) -> String {
+    let mut disassembler = Disassembler { data, options, accumulator_16: false, index_16: false };
+    let lines = disassembler.lines();
+
+    let starts: HashSet<u32> = lines.iter().map(|line| match line {
+        Line::Instr(_, _, range) | Line::Data(range) => disassembler.address(range.start)
+    }).chain([disassembler.address(data.len())]).collect();
+
+    let labels: HashSet<u32> = lines.iter().filter_map(|line| match line {
+        Line::Instr(instr, info, range) => disassembler.target(*instr, info, range),
+        Line::Data(_) => None
+    }).filter(|target| starts.contains(target)).collect();
+
+    let mut output = String::new();
+    if options.cpu != CpuType::default() {
+        let _ = writeln!(output, ".cpu \"{}\"", options.cpu.name());
+    }
+    let _ = writeln!(output, ".org {}", hex(options.start));
+
+    for line in lines.iter() {
+        let range = match line {
+            Line::Instr(_, _, range) | Line::Data(range) => range
+        };
+
+        let address = disassembler.address(range.start);
+        if labels.contains(&address) {
+            let _ = writeln!(output, "{}:", label_name(address));
+        }
+
+        let _ = match line {
+            Line::Instr(instr, info, range) => writeln!(output, "    {}", disassembler.format_instr(*instr, info, range, &labels)),
+            Line::Data(range) => writeln!(output, "    .byte {}", data[range.clone()].iter().map(|byte| format!("${:02X}", byte)).collect::<Vec<_>>().join(", "))
+        };
+    }
+
+    let end = disassembler.address(data.len());
+    if labels.contains(&end) {
+        let _ = writeln!(output, "{}:", label_name(end));
+    }
+
+    output
+}
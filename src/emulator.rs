@@ -0,0 +1,486 @@
+use std::collections::VecDeque;
+
+use thiserror::Error;
+
+use crate::opcode::{decode_opcode, CpuType, ModeType, INSTS};
+
+pub const DEFAULT_MAX_CYCLES: u64 = 100_000_000;
+
+/* Program counter after the RTS of a called routine */
+pub const RETURN_ADDRESS: u16 = 0x0000;
+
+/* Processor status flags */
+pub const FLAG_CARRY: u8 = 0x01;
+pub const FLAG_ZERO: u8 = 0x02;
+pub const FLAG_INTERRUPT: u8 = 0x04;
+pub const FLAG_DECIMAL: u8 = 0x08;
+pub const FLAG_BREAK: u8 = 0x10;
+pub const FLAG_UNUSED: u8 = 0x20;
+pub const FLAG_OVERFLOW: u8 = 0x40;
+pub const FLAG_NEGATIVE: u8 = 0x80;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EmulatorError {
+    #[error("Illegal opcode ${0:02X} at ${1:04X}")]
+    IllegalOpcode(u8, u16),
+    #[error("Cycle limit ({0}) exceeded at ${1:04X}")]
+    CycleLimit(u64, u16),
+    #[error("Program does not fit in memory, it ends at ${0:X}")]
+    ProgramTooBig(usize)
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum StopReason {
+    /// BRK instruction, it is not executed
+    Break,
+
+    /// Jump or branch to itself
+    Loop,
+
+    /// RTS of the first called routine
+    Return
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Operand {
+    None,
+    Accumulator,
+    Address(u16)
+}
+
+/* NMOS 6502 with flat 64K RAM, stdout and stdin ports are memory mapped */
+pub struct Emulator {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub s: u8,
+    pub p: u8,
+    pub pc: u16,
+    pub cycles: u64,
+    pub memory: Vec<u8>,
+    pub stdout_port: Option<u16>,
+    pub stdin_port: Option<u16>,
+    pub output: Vec<u8>,
+    pub input: VecDeque<u8>
+}
+
+fn is_read_modify_write(name: &str) -> bool {
+    ["ASL", "LSR", "ROL", "ROR", "INC", "DEC"].contains(&name)
+}
+
+fn is_store(name: &str) -> bool {
+    ["STA", "STX", "STY"].contains(&name)
+}
+
+fn page_crossed(first: u16, second: u16) -> bool {
+    first & 0xff00 != second & 0xff00
+}
+
+/* Cycles without page cross and branch penalties */
+fn base_cycles(name: &str, mode: ModeType) -> u64 {
+    let rmw = is_read_modify_write(name);
+    match mode {
+        ModeType::Implied | ModeType::Accumulator => match name {
+            "PHA" | "PHP" => 3,
+            "PLA" | "PLP" => 4,
+            "RTS" | "RTI" => 6,
+            "BRK" => 7,
+            _ => 2
+        },
+        ModeType::ZeroPage if rmw => 5,
+        ModeType::ZeroPage => 3,
+        ModeType::ZeroPageX | ModeType::ZeroPageY if rmw => 6,
+        ModeType::ZeroPageX | ModeType::ZeroPageY => 4,
+        ModeType::Absolute => match name {
+            "JMP" => 3,
+            "JSR" => 6,
+            _ if rmw => 6,
+            _ => 4
+        },
+        ModeType::AbsoluteX | ModeType::AbsoluteY if rmw => 7,
+        ModeType::AbsoluteX | ModeType::AbsoluteY if is_store(name) => 5,
+        ModeType::AbsoluteX | ModeType::AbsoluteY => 4,
+        ModeType::IndirectX => 6,
+        ModeType::IndirectY if is_store(name) => 6,
+        ModeType::IndirectY => 5,
+        ModeType::Indirect => 5,
+        _ => 2
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator {
+    pub fn new() -> Self {
+        Self {
+            a: 0,
+            x: 0,
+            y: 0,
+            s: 0xfd,
+            p: FLAG_UNUSED | FLAG_INTERRUPT,
+            pc: 0,
+            cycles: 0,
+            memory: vec![0; 0x10000],
+            stdout_port: None,
+            stdin_port: None,
+            output: Vec::new(),
+            input: VecDeque::new()
+        }
+    }
+
+    pub fn load(&mut self, address: u32, data: &[u8]) -> Result<(), EmulatorError> {
+        let end = address as usize + data.len();
+        if end > self.memory.len() {
+            return Err(EmulatorError::ProgramTooBig(end));
+        }
+
+        self.memory[address as usize..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn read(&mut self, address: u16) -> u8 {
+        match self.stdin_port == Some(address) {
+            true => self.input.pop_front().unwrap_or_default(),
+            false => self.memory[address as usize]
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match self.stdout_port == Some(address) {
+            true => self.output.push(value),
+            false => self.memory[address as usize] = value
+        }
+    }
+
+    /* High byte comes from the same page (ex. zero page pointers and JMP ($xxFF) bug) */
+    fn read_word_in_page(&mut self, address: u16) -> u16 {
+        let high = (address & 0xff00) | (address.wrapping_add(1) & 0x00ff);
+        self.read(address) as u16 | (self.read(high) as u16) << 8
+    }
+
+    fn fetch(&mut self) -> u8 {
+        let value = self.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        value
+    }
+
+    fn fetch_word(&mut self) -> u16 {
+        self.fetch() as u16 | (self.fetch() as u16) << 8
+    }
+
+    fn push(&mut self, value: u8) {
+        self.write(0x0100 | self.s as u16, value);
+        self.s = self.s.wrapping_sub(1);
+    }
+
+    fn pull(&mut self) -> u8 {
+        self.s = self.s.wrapping_add(1);
+        self.read(0x0100 | self.s as u16)
+    }
+
+    fn set_flag(&mut self, flag: u8, value: bool) {
+        match value {
+            true => self.p |= flag,
+            false => self.p &= !flag
+        };
+    }
+
+    fn flag(&self, flag: u8) -> bool {
+        self.p & flag != 0
+    }
+
+    fn set_zero_negative(&mut self, value: u8) {
+        self.set_flag(FLAG_ZERO, value == 0);
+        self.set_flag(FLAG_NEGATIVE, value & 0x80 != 0);
+    }
+
+    /* Effective address and page cross information */
+    fn operand(&mut self, mode: ModeType) -> (Operand, bool) {
+        match mode {
+            ModeType::Implied => (Operand::None, false),
+            ModeType::Accumulator => (Operand::Accumulator, false),
+            ModeType::Immediate | ModeType::Relative => {
+                let address = self.pc;
+                self.pc = self.pc.wrapping_add(1);
+                (Operand::Address(address), false)
+            },
+            ModeType::ZeroPage => (Operand::Address(self.fetch() as u16), false),
+            ModeType::ZeroPageX => (Operand::Address(self.fetch().wrapping_add(self.x) as u16), false),
+            ModeType::ZeroPageY => (Operand::Address(self.fetch().wrapping_add(self.y) as u16), false),
+            ModeType::Absolute => (Operand::Address(self.fetch_word()), false),
+            ModeType::AbsoluteX | ModeType::AbsoluteY => {
+                let base = self.fetch_word();
+                let index = if mode == ModeType::AbsoluteX { self.x } else { self.y };
+                let address = base.wrapping_add(index as u16);
+                (Operand::Address(address), page_crossed(base, address))
+            },
+            ModeType::IndirectX => {
+                let pointer = self.fetch().wrapping_add(self.x);
+                (Operand::Address(self.read_word_in_page(pointer as u16)), false)
+            },
+            ModeType::IndirectY => {
+                let pointer = self.fetch();
+                let base = self.read_word_in_page(pointer as u16);
+                let address = base.wrapping_add(self.y as u16);
+                (Operand::Address(address), page_crossed(base, address))
+            },
+            ModeType::Indirect => {
+                let pointer = self.fetch_word();
+                (Operand::Address(self.read_word_in_page(pointer)), false)
+            },
+            _ => (Operand::None, false)
+        }
+    }
+
+    fn load_operand(&mut self, operand: Operand) -> u8 {
+        match operand {
+            Operand::Address(address) => self.read(address),
+            _ => self.a
+        }
+    }
+
+    fn store_operand(&mut self, operand: Operand, value: u8) {
+        match operand {
+            Operand::Address(address) => self.write(address, value),
+            _ => self.a = value
+        };
+    }
+
+    fn compare(&mut self, register: u8, value: u8) {
+        self.set_flag(FLAG_CARRY, register >= value);
+        self.set_zero_negative(register.wrapping_sub(value));
+    }
+
+    /* NMOS decimal mode sets Z from the binary result and N, V from the high digit */
+    fn adc(&mut self, value: u8) {
+        let carry = self.flag(FLAG_CARRY) as u16;
+        let binary = self.a as u16 + value as u16 + carry;
+
+        if !self.flag(FLAG_DECIMAL) {
+            self.set_flag(FLAG_CARRY, binary > 0xff);
+            self.set_flag(FLAG_OVERFLOW, (!(self.a ^ value) & (self.a ^ binary as u8) & 0x80) != 0);
+            self.a = binary as u8;
+            self.set_zero_negative(self.a);
+            return;
+        }
+
+        let mut low = (self.a & 0x0f) as u16 + (value & 0x0f) as u16 + carry;
+        let mut high = (self.a >> 4) as u16 + (value >> 4) as u16;
+        if low > 9 {
+            low += 6;
+        }
+        if low > 0x0f {
+            high += 1;
+        }
+
+        self.set_flag(FLAG_ZERO, binary & 0xff == 0);
+        self.set_flag(FLAG_NEGATIVE, high & 0x08 != 0);
+        self.set_flag(FLAG_OVERFLOW, (!(self.a ^ value) & (self.a ^ (high << 4) as u8) & 0x80) != 0);
+
+        if high > 9 {
+            high += 6;
+        }
+        self.set_flag(FLAG_CARRY, high > 0x0f);
+        self.a = ((high << 4) | (low & 0x0f)) as u8;
+    }
+
+    /* NMOS decimal mode sets the flags from the binary result */
+    fn sbc(&mut self, value: u8) {
+        let borrow = !self.flag(FLAG_CARRY) as i16;
+        let binary = self.a as i16 - value as i16 - borrow;
+        let result = binary as u8;
+
+        self.set_flag(FLAG_CARRY, binary >= 0);
+        self.set_flag(FLAG_OVERFLOW, ((self.a ^ value) & (self.a ^ result) & 0x80) != 0);
+        self.set_zero_negative(result);
+
+        if !self.flag(FLAG_DECIMAL) {
+            self.a = result;
+            return;
+        }
+
+        let mut low = (self.a & 0x0f) as i16 - (value & 0x0f) as i16 - borrow;
+        let mut high = (self.a >> 4) as i16 - (value >> 4) as i16;
+        if low < 0 {
+            low -= 6;
+            high -= 1;
+        }
+        if high < 0 {
+            high -= 6;
+        }
+        self.a = ((high << 4) | (low & 0x0f)) as u8;
+    }
+
+    fn branch(&mut self, operand: Operand, condition: bool) {
+        let offset = self.load_operand(operand) as i8;
+        if condition {
+            let target = self.pc.wrapping_add(offset as u16);
+            self.cycles += 1 + page_crossed(self.pc, target) as u64;
+            self.pc = target;
+        }
+    }
+
+    /* Executes one instruction, returns the stop reason if the program is finished */
+    pub fn step(&mut self, return_stack: u8) -> Result<Option<StopReason>, EmulatorError> {
+        let address = self.pc;
+        let opcode = self.read(address);
+
+        let (instr, info) = match decode_opcode(opcode, CpuType::Mos6502) {
+            Some(decoded) => decoded,
+            None => return Err(EmulatorError::IllegalOpcode(opcode, address))
+        };
+
+        let name = INSTS[instr];
+        if name == "BRK" {
+            return Ok(Some(StopReason::Break));
+        }
+
+        self.pc = self.pc.wrapping_add(1);
+        let (operand, crossed) = self.operand(info.mode);
+        self.cycles += base_cycles(name, info.mode);
+
+        if crossed && !is_store(name) && !is_read_modify_write(name) {
+            self.cycles += 1;
+        }
+
+        match name {
+            "LDA" => { self.a = self.load_operand(operand); self.set_zero_negative(self.a); },
+            "LDX" => { self.x = self.load_operand(operand); self.set_zero_negative(self.x); },
+            "LDY" => { self.y = self.load_operand(operand); self.set_zero_negative(self.y); },
+            "STA" => self.store_operand(operand, self.a),
+            "STX" => self.store_operand(operand, self.x),
+            "STY" => self.store_operand(operand, self.y),
+            "TAX" => { self.x = self.a; self.set_zero_negative(self.x); },
+            "TAY" => { self.y = self.a; self.set_zero_negative(self.y); },
+            "TXA" => { self.a = self.x; self.set_zero_negative(self.a); },
+            "TYA" => { self.a = self.y; self.set_zero_negative(self.a); },
+            "TSX" => { self.x = self.s; self.set_zero_negative(self.x); },
+            "TXS" => self.s = self.x,
+            "PHA" => self.push(self.a),
+            "PHP" => self.push(self.p | FLAG_BREAK | FLAG_UNUSED),
+            "PLA" => { self.a = self.pull(); self.set_zero_negative(self.a); },
+            "PLP" => self.p = (self.pull() & !FLAG_BREAK) | FLAG_UNUSED,
+            "AND" => { self.a &= self.load_operand(operand); self.set_zero_negative(self.a); },
+            "ORA" => { self.a |= self.load_operand(operand); self.set_zero_negative(self.a); },
+            "EOR" => { self.a ^= self.load_operand(operand); self.set_zero_negative(self.a); },
+            "BIT" => {
+                let value = self.load_operand(operand);
+                self.set_flag(FLAG_ZERO, self.a & value == 0);
+                self.set_flag(FLAG_NEGATIVE, value & 0x80 != 0);
+                self.set_flag(FLAG_OVERFLOW, value & 0x40 != 0);
+            },
+            "ADC" => { let value = self.load_operand(operand); self.adc(value); },
+            "SBC" => { let value = self.load_operand(operand); self.sbc(value); },
+            "CMP" => { let value = self.load_operand(operand); self.compare(self.a, value); },
+            "CPX" => { let value = self.load_operand(operand); self.compare(self.x, value); },
+            "CPY" => { let value = self.load_operand(operand); self.compare(self.y, value); },
+            "INC" | "DEC" | "ASL" | "LSR" | "ROL" | "ROR" => {
+                let value = self.load_operand(operand);
+                let carry = self.flag(FLAG_CARRY) as u8;
+                let result = match name {
+                    "INC" => value.wrapping_add(1),
+                    "DEC" => value.wrapping_sub(1),
+                    "ASL" => { self.set_flag(FLAG_CARRY, value & 0x80 != 0); value << 1 },
+                    "LSR" => { self.set_flag(FLAG_CARRY, value & 0x01 != 0); value >> 1 },
+                    "ROL" => { self.set_flag(FLAG_CARRY, value & 0x80 != 0); (value << 1) | carry },
+                    _ => { self.set_flag(FLAG_CARRY, value & 0x01 != 0); (value >> 1) | (carry << 7) }
+                };
+                self.store_operand(operand, result);
+                self.set_zero_negative(result);
+            },
+            "INX" => { self.x = self.x.wrapping_add(1); self.set_zero_negative(self.x); },
+            "INY" => { self.y = self.y.wrapping_add(1); self.set_zero_negative(self.y); },
+            "DEX" => { self.x = self.x.wrapping_sub(1); self.set_zero_negative(self.x); },
+            "DEY" => { self.y = self.y.wrapping_sub(1); self.set_zero_negative(self.y); },
+            "CLC" => self.set_flag(FLAG_CARRY, false),
+            "SEC" => self.set_flag(FLAG_CARRY, true),
+            "CLI" => self.set_flag(FLAG_INTERRUPT, false),
+            "SEI" => self.set_flag(FLAG_INTERRUPT, true),
+            "CLD" => self.set_flag(FLAG_DECIMAL, false),
+            "SED" => self.set_flag(FLAG_DECIMAL, true),
+            "CLV" => self.set_flag(FLAG_OVERFLOW, false),
+            "BCC" => self.branch(operand, !self.flag(FLAG_CARRY)),
+            "BCS" => self.branch(operand, self.flag(FLAG_CARRY)),
+            "BNE" => self.branch(operand, !self.flag(FLAG_ZERO)),
+            "BEQ" => self.branch(operand, self.flag(FLAG_ZERO)),
+            "BPL" => self.branch(operand, !self.flag(FLAG_NEGATIVE)),
+            "BMI" => self.branch(operand, self.flag(FLAG_NEGATIVE)),
+            "BVC" => self.branch(operand, !self.flag(FLAG_OVERFLOW)),
+            "BVS" => self.branch(operand, self.flag(FLAG_OVERFLOW)),
+            "JMP" => if let Operand::Address(target) = operand { self.pc = target },
+            "JSR" => {
+                let return_address = self.pc.wrapping_sub(1);
+                self.push((return_address >> 8) as u8);
+                self.push(return_address as u8);
+                if let Operand::Address(target) = operand { self.pc = target }
+            },
+            "RTS" => {
+                let stack = self.s;
+                self.pc = (self.pull() as u16 | (self.pull() as u16) << 8).wrapping_add(1);
+
+                /* Returning from the first routine */
+                if stack >= return_stack {
+                    return Ok(Some(StopReason::Return));
+                }
+            },
+            "RTI" => {
+                self.p = (self.pull() & !FLAG_BREAK) | FLAG_UNUSED;
+                self.pc = self.pull() as u16 | (self.pull() as u16) << 8;
+            },
+            _ => ()
+        };
+
+        match self.pc == address {
+            true => Ok(Some(StopReason::Loop)),
+            false => Ok(None)
+        }
+    }
+
+    /* Runs until BRK, endless loop or RTS from the starting stack level */
+    pub fn run(&mut self, max_cycles: u64) -> Result<StopReason, EmulatorError> {
+        let return_stack = self.s;
+
+        loop {
+            if self.cycles >= max_cycles {
+                return Err(EmulatorError::CycleLimit(max_cycles, self.pc));
+            }
+
+            if let Some(reason) = self.step(return_stack)? {
+                return Ok(reason);
+            }
+        }
+    }
+
+    /* Calls the routine like JSR, the emulator stops after its RTS. JSR and RTS cycles are counted */
+    pub fn call(&mut self, address: u16, max_cycles: u64) -> Result<StopReason, EmulatorError> {
+        self.push((RETURN_ADDRESS.wrapping_sub(1) >> 8) as u8);
+        self.push(RETURN_ADDRESS.wrapping_sub(1) as u8);
+        self.cycles += base_cycles("JSR", ModeType::Absolute);
+        self.pc = address;
+        self.run(max_cycles)
+    }
+
+    pub fn flags_text(&self) -> String {
+        "NV-BDIZC".chars().enumerate().map(|(index, flag)| match self.p & (0x80 >> index) != 0 {
+            true => flag,
+            false => flag.to_ascii_lowercase()
+        }).collect()
+    }
+
+    pub fn registers_text(&self) -> String {
+        format!("PC=${:04X} A=${:02X} X=${:02X} Y=${:02X} S=${:02X} P=${:02X} ({}) Cycles={}", self.pc, self.a, self.x, self.y, self.s, self.p, self.flags_text(), self.cycles)
+    }
+
+    pub fn memory_text(&self, start: u16, end: u16) -> String {
+        let mut output = String::new();
+        for line in (start as usize..=end as usize).step_by(16) {
+            let bytes: Vec<String> = (line..=(line + 15).min(end as usize)).map(|address| format!("{:02X}", self.memory[address])).collect();
+            output.push_str(&format!("{:04X}: {}\n", line, bytes.join(" ")));
+        }
+        output
+    }
+}
@@ -0,0 +1,200 @@
+use std::{fmt::Write, ops::Range, rc::Rc};
+
+use crate::{ast::Ast, code_gen::CodeGenerator, context::Context, parser::MacroCall};
+
+const BYTES_PER_LINE: usize = 4;
+const MAX_BYTE_LINES: usize = 8;
+
+/* Source line of an ast, macro expansions are listed after the outermost macro call line */
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct LineKey {
+    file_id: usize,
+    line: usize,
+    expansion: Option<(usize, usize)>
+}
+
+struct Group {
+    key: LineKey,
+    address: usize,
+    range: Range<usize>
+}
+
+struct Listing<'a> {
+    output: String,
+    files: Vec<Vec<String>>,
+    context: &'a Context,
+
+    /* Included files and the next line to print in them */
+    stack: Vec<(usize, usize)>
+}
+
+fn outermost_call(macro_call: &Option<Rc<MacroCall>>) -> Option<&Rc<MacroCall>> {
+    let mut call = macro_call.as_ref()?;
+    while let Some(parent) = call.parent.as_ref() {
+        call = parent;
+    }
+    Some(call)
+}
+
+impl<'a> Listing<'a> {
+    fn new(context: &'a Context) -> Self {
+        let files = context.code_files.borrow().iter()
+            .map(|file| String::from_utf8_lossy(&file.data).lines().map(|line| line.to_owned()).collect())
+            .collect();
+
+        Self { output: String::new(), files, context, stack: Vec::new() }
+    }
+
+    fn source(&self, file_id: usize, line: usize) -> &str {
+        self.files.get(file_id).and_then(|lines| lines.get(line)).map(|line| line.as_str()).unwrap_or_default()
+    }
+
+    fn header(&mut self, file_id: usize) {
+        let path = self.context.code_files.borrow().get(file_id).map(|file| file.path.display().to_string()).unwrap_or_default();
+        let _ = writeln!(self.output, "; {}", path);
+    }
+
+    fn enter(&mut self, file_id: usize) {
+        self.stack.push((file_id, 0));
+        self.header(file_id);
+    }
+
+    /* Print the rest of the file and go back to the file that includes it */
+    fn leave(&mut self) {
+        if let Some((file_id, _)) = self.stack.last().copied() {
+            self.flush(self.files.get(file_id).map(|lines| lines.len()).unwrap_or_default());
+            self.stack.pop();
+
+            if let Some((file_id, _)) = self.stack.last().copied() {
+                self.header(file_id);
+            }
+        }
+    }
+
+    fn switch_file(&mut self, file_id: usize) {
+        if !self.stack.iter().any(|(id, _)| *id == file_id) {
+            self.enter(file_id);
+            return;
+        }
+
+        while self.stack.last().map(|(id, _)| *id != file_id).unwrap_or_default() {
+            self.leave();
+        }
+    }
+
+    /* Lines without generated codes */
+    fn flush(&mut self, end: usize) {
+        if let Some((file_id, next_line)) = self.stack.last().copied() {
+            for line in next_line..end {
+                let text = self.source(file_id, line).to_owned();
+                self.write_line(line, ' ', None, &[], &text);
+            }
+
+            if let Some(top) = self.stack.last_mut() {
+                top.1 = top.1.max(end);
+            }
+        }
+    }
+
+    fn write_line(&mut self, line: usize, marker: char, address: Option<usize>, bytes: &[u8], text: &str) {
+        let address = address.map(|address| format!("{:04X}", address)).unwrap_or_default();
+        let mut chunks = bytes.chunks(BYTES_PER_LINE);
+        let first = chunks.next().map(hex_bytes).unwrap_or_default();
+
+        let row = format!("{:>5}{} {:<6} {:<11}  {}", line + 1, marker, address, first, text);
+        let _ = writeln!(self.output, "{}", row.trim_end());
+
+        for (index, chunk) in chunks.enumerate() {
+            if index + 2 == MAX_BYTE_LINES && bytes.len() > BYTES_PER_LINE * MAX_BYTE_LINES {
+                let _ = writeln!(self.output, "{:>6} {:<6} ...", "", "");
+                break;
+            }
+
+            let _ = writeln!(self.output, "{:>6} {:<6} {}", "", "", hex_bytes(chunk));
+        }
+    }
+
+    fn start_group(&mut self, key: LineKey) {
+        self.switch_file(key.file_id);
+
+        let next_line = self.stack.last().map(|(_, next_line)| *next_line).unwrap_or_default();
+        if key.line >= next_line {
+            /* Macro call line is printed before the expansion lines */
+            match key.expansion {
+                Some(_) => self.flush(key.line + 1),
+                None => self.flush(key.line)
+            };
+
+            if let Some(top) = self.stack.last_mut() {
+                top.1 = key.line + 1;
+            }
+        }
+    }
+
+    fn write_group(&mut self, group: &Group, target: &[u8]) {
+        let bytes = target.get(group.range.clone()).unwrap_or_default();
+
+        match group.key.expansion {
+            Some((file_id, line)) => {
+                let text = self.source(file_id, line).to_owned();
+                self.write_line(line, '+', Some(group.address), bytes, &text);
+            },
+            None => {
+                let text = self.source(group.key.file_id, group.key.line).to_owned();
+                self.write_line(group.key.line, ' ', Some(group.address), bytes, &text);
+            }
+        };
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+/* Line number, address, generated bytes and source text. Lines generated by macros are marked with '+' */
+pub fn generate_listing(generator: &CodeGenerator, context: &Context) -> String {
+    let asts = context.asts.borrow();
+    let mut listing = Listing::new(context);
+    let mut group: Option<Group> = None;
+
+    listing.enter(0);
+
+    for (ast, (address, range)) in asts.iter().zip(generator.ast_ranges.iter()) {
+        let key = match outermost_call(&ast.macro_call) {
+            Some(call) => LineKey { file_id: call.file_id, line: call.line, expansion: Some((ast.file_id, ast.line)) },
+            None => LineKey { file_id: ast.file_id, line: ast.line, expansion: None }
+        };
+
+        /* Labels are merged with the codes on the same line, repeated lines are listed again */
+        if let Some(current) = group.as_mut() {
+            if current.key == key && current.range.is_empty() && current.range.end == range.start {
+                current.range.end = range.end;
+                continue;
+            }
+        }
+
+        if let Some(current) = group.take() {
+            listing.write_group(&current, &context.target);
+        }
+
+        listing.start_group(key);
+        group = Some(Group { key, address: *address, range: range.clone() });
+
+        if let Ast::Include(file_id) = ast.ast {
+            if let Some(current) = group.take() {
+                listing.write_group(&current, &context.target);
+            }
+            listing.enter(file_id);
+        }
+    }
+
+    if let Some(current) = group.take() {
+        listing.write_group(&current, &context.target);
+    }
+
+    while !listing.stack.is_empty() {
+        listing.leave();
+    }
+
+    listing.output
+}
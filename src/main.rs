@@ -5,20 +5,35 @@ mod ast;
 mod directive;
 mod tool;
 mod context;
+mod output;
+mod listing;
+mod symbols;
+mod disasm;
+mod emulator;
+mod unit_test;
 #[cfg(test)]
 mod tests;
 
-use std::{fs::File, io::{Read, Write}, path::PathBuf};
+use std::{fs::File, io::{Read, Write}, ops::RangeInclusive, path::{Path, PathBuf}};
 
 use log::{error, info, LevelFilter};
 use simplelog::*;
 
 use ast::{AstGenerator, AstGeneratorError};
 use code_gen::{CodeGenerator, CodeGeneratorError};
-use context::Context;
+use context::{Context, DEFAULT_MAX_ERRORS};
+use directive::DirectiveValue;
+use disasm::{disassemble, DisasmOptions};
+use emulator::{Emulator, EmulatorError, StopReason, DEFAULT_MAX_CYCLES};
 use parser::{ParseError, Parser};
+use opcode::CpuType;
+use listing::generate_listing;
+use output::{generate_output, OutputError, OutputFormat};
+use symbols::{generate_symbols, SymbolFormat};
+use unit_test::{embedded_spec, file_spec, parse_spec, run_test, TestSpecError};
+use tool::{parse_address, parse_cpu, parse_define, parse_number, parse_range};
 
-use clap::{arg, command, Parser as ClapParser};
+use clap::{Parser as ClapParser, Subcommand};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -35,17 +50,34 @@ pub enum StarterError {
     #[error("{0}")]
     Ast(#[from] AstGeneratorError),
 
+    #[error("{0}")]
+    Output(#[from] OutputError),
+
+    #[error("{0}")]
+    Emulator(#[from] EmulatorError),
+
+    #[error("'{0}' is not a valid address or branch name")]
+    UnknownAddress(String),
+
+    #[error("{0}")]
+    TestSpec(#[from] TestSpecError),
+
+    #[error("{0} of {1} tests failed")]
+    TestFailed(usize, usize),
+
     #[error("Please specify on of the argument [--target, --binary_dump, --token_dump]")]
     InvalidArgument
 }
 
 #[derive(ClapParser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 
     /// Source .asm file
-    #[arg(value_name = "SOURCE-FILE")]
-    source: PathBuf,
+    #[arg(value_name = "SOURCE-FILE", required = true)]
+    source: Option<PathBuf>,
 
     /// Target binary
     #[arg(long, value_name = "TARGET-FILE")]
@@ -62,9 +94,150 @@ struct Cli {
     /// Silent mode
     #[clap(long, short, action)]
     silent: bool,
+
+    /// Define a const before compilation, value is 1 if not specified
+    #[arg(short = 'D', long = "define", value_name = "NAME[=VALUE]", value_parser = parse_define)]
+    defines: Vec<(String, DirectiveValue)>,
+
+    /// Target file format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Binary)]
+    format: OutputFormat,
+
+    /// Add '10 SYS <start>' BASIC line to prg output, loaded at BASIC start address (default $0801, $1001 for VIC-20)
+    #[arg(long, value_name = "BASIC-START", num_args = 0..=1, default_missing_value = "$0801", value_parser = parse_number)]
+    basic_stub: Option<u16>,
+
+    /// Write listing with addresses, generated bytes and source lines
+    #[arg(long, value_name = "LISTING-FILE")]
+    listing: Option<PathBuf>,
+
+    /// Export labels for emulators and debuggers, FCEUX format uses it as ROM file name
+    #[arg(long, value_name = "SYMBOL-FILE")]
+    symbols: Option<PathBuf>,
+
+    /// Symbol file format
+    #[arg(long, value_enum, default_value_t = SymbolFormat::Json)]
+    symbol_format: SymbolFormat,
+
+    /// Target CPU, .cpu directive could change it
+    #[arg(long, value_name = "CPU", default_value = "6502", value_parser = parse_cpu)]
+    cpu: CpuType,
+
+    /// Maximum number of printed errors
+    #[arg(long, value_name = "COUNT", default_value_t = DEFAULT_MAX_ERRORS)]
+    max_errors: usize,
+
+    /// Rewrite out of range branches as inverted branch and JMP, .longbranch directive could change it
+    #[arg(long)]
+    relax_branches: bool,
 }
 
 
+#[derive(Subcommand)]
+enum Command {
+    /// Disassemble a binary file to source code
+    Disasm(DisasmArgs),
+
+    /// Assemble and run the program on NMOS 6502 emulator
+    Run(RunArgs),
+
+    /// Assemble and run the test blocks on NMOS 6502 emulator
+    Test(TestArgs)
+}
+
+#[derive(clap::Args)]
+struct DisasmArgs {
+    /// Binary file
+    #[arg(value_name = "BINARY-FILE")]
+    binary: PathBuf,
+
+    /// Target source file, source code is printed if not specified
+    #[arg(long, value_name = "TARGET-FILE")]
+    target: Option<PathBuf>,
+
+    /// Address of the first byte
+    #[arg(long, value_name = "ADDRESS", default_value = "0", value_parser = parse_address)]
+    start: u32,
+
+    /// Target CPU
+    #[arg(long, value_name = "CPU", default_value = "6502", value_parser = parse_cpu)]
+    cpu: CpuType,
+
+    /// Disassemble only the given address range (ex. $C000-$C0FF), could be used many times
+    #[arg(long, value_name = "START-END", value_parser = parse_range)]
+    code: Vec<RangeInclusive<u32>>,
+
+    /// Write the given address range as bytes (ex. $C100-$C1FF), could be used many times
+    #[arg(long, value_name = "START-END", value_parser = parse_range)]
+    data: Vec<RangeInclusive<u32>>,
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    /// Source .asm file
+    #[arg(value_name = "SOURCE-FILE")]
+    source: PathBuf,
+
+    /// Define a const before compilation, value is 1 if not specified
+    #[arg(short = 'D', long = "define", value_name = "NAME[=VALUE]", value_parser = parse_define)]
+    defines: Vec<(String, DirectiveValue)>,
+
+    /// Start address or branch name, first .org address is used if not specified
+    #[arg(long, value_name = "ADDRESS")]
+    entry: Option<String>,
+
+    /// Bytes written to this address are printed
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_number)]
+    stdout: Option<u16>,
+
+    /// Reading this address returns the next byte of the standard input, 0 after the end
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_number)]
+    stdin: Option<u16>,
+
+    /// Stop with error after this many cycles
+    #[arg(long, value_name = "CYCLES", default_value_t = DEFAULT_MAX_CYCLES)]
+    max_cycles: u64,
+
+    /// Print the memory range after the execution (ex. $0200-$02FF), could be used many times
+    #[arg(long, value_name = "START-END", value_parser = parse_range)]
+    dump: Vec<RangeInclusive<u32>>,
+
+    /// Maximum number of printed errors
+    #[arg(long, value_name = "COUNT", default_value_t = DEFAULT_MAX_ERRORS)]
+    max_errors: usize,
+
+    /// Silent mode
+    #[clap(long, short, action)]
+    silent: bool,
+}
+
+#[derive(clap::Args)]
+struct TestArgs {
+    /// Source .asm file
+    #[arg(value_name = "SOURCE-FILE")]
+    source: PathBuf,
+
+    /// Define a const before compilation, value is 1 if not specified
+    #[arg(short = 'D', long = "define", value_name = "NAME[=VALUE]", value_parser = parse_define)]
+    defines: Vec<(String, DirectiveValue)>,
+
+    /// Test spec file, the ';!' comments in the source files are used if not specified
+    #[arg(long, value_name = "SPEC-FILE")]
+    spec: Option<PathBuf>,
+
+    /// Fail the test after this many cycles in a call
+    #[arg(long, value_name = "CYCLES", default_value_t = DEFAULT_MAX_CYCLES)]
+    max_cycles: u64,
+
+    /// Maximum number of printed errors
+    #[arg(long, value_name = "COUNT", default_value_t = DEFAULT_MAX_ERRORS)]
+    max_errors: usize,
+
+    /// Silent mode
+    #[clap(long, short, action)]
+    silent: bool,
+}
+
 fn read_file(path: PathBuf) -> Result<Vec<u8>, StarterError> {
     let mut file = File::open(&path)?;    
     let mut code = Vec::new();
@@ -72,50 +245,203 @@ fn read_file(path: PathBuf) -> Result<Vec<u8>, StarterError> {
     Ok(code)
 }
 
-fn execute(cli: &Cli) -> Result<(), StarterError> {
-    if !cli.binary_dump && !cli.token_dump && cli.target.is_none() {
-        return Err(StarterError::InvalidArgument);
-    }
+fn execute_disasm(args: &DisasmArgs) -> Result<(), StarterError> {
+    let data = read_file(args.binary.clone())?;
+    let options = DisasmOptions {
+        start: args.start,
+        cpu: args.cpu,
+        code: args.code.clone(),
+        data: args.data.clone()
+    };
 
-    if !cli.silent {
-        info!("timu6502asm Compiler");
-    }
+    let source = disassemble(&data, &options);
+    match &args.target {
+        Some(target) => File::create(target)?.write_all(source.as_bytes())?,
+        None => print!("{}", source)
+    };
+    Ok(())
+}
+
+fn assemble(source: &Path, defines: &[(String, DirectiveValue)], cpu: CpuType, silent: bool, token_dump: bool, max_errors: usize, relax_branches: bool) -> Result<(CodeGenerator, Context), StarterError> {
+    let context = Context {
+        silent,
+        max_errors,
+        cpu,
+        ..Default::default()
+    };
 
-    let mut context = Context::default();
-    context.silent = cli.silent;
+    for (name, value) in defines.iter() {
+        context.references.borrow_mut().insert(name.clone(), vec![value.clone()]);
+    }
     
-    if !cli.silent {
-        info!("Compiling {:?}", &cli.source.as_os_str());
+    if !silent {
+        info!("Compiling {:?}", source.as_os_str());
     }
 
-    let data = read_file(cli.source.clone())?;
+    let data = read_file(source.to_path_buf())?;
 
-    context.add_file(0, cli.source.clone());
+    context.add_file(0, source.to_path_buf());
     context.code_files.borrow_mut()[0].data = data.clone();
 
     let mut parser = Parser::new(0, &data, context);
-    parser.parse()?;
-    if cli.token_dump {
+    let parsed = parser.parse();
+    if token_dump {
         parser.friendly_dump();
     }
 
-    let context = parser.context;
+    /* Every stage runs after the errors of the previous stages until the error limit, the first error is returned */
+    let mut context = parser.context;
 
     let ast_generator = AstGenerator::new();
-    let context = ast_generator.generate(context)?;
+    let generated = match context.error_limit_reached() {
+        true => Ok(()),
+        false => ast_generator.generate_in_place(&context)
+    };
 
     let mut generator = CodeGenerator::new();
-    generator.silent = cli.silent;
+    generator.silent = silent;
+    generator.cpu = cpu;
+    generator.relax_branches = relax_branches;
+
+    let compiled = match context.error_limit_reached() {
+        true => Ok(()),
+        false => generator.generate_in_place(&mut context)
+    };
 
-    let context = generator.generate(context)?;
+    parsed?;
+    generated?;
+    compiled?;
+    Ok((generator, context))
+}
+
+/* Address or branch name */
+fn resolve_address(generator: &CodeGenerator, text: &str) -> Result<u16, StarterError> {
+    match (parse_number(text), generator.branches.get(text)) {
+        (Ok(address), _) => Ok(address),
+        (_, Some(address)) if *address <= 0xffff => Ok(*address as u16),
+        _ => Err(StarterError::UnknownAddress(text.to_owned()))
+    }
+}
+
+fn execute_run(args: &RunArgs) -> Result<(), StarterError> {
+    let (generator, context) = assemble(&args.source, &args.defines, CpuType::Mos6502, args.silent, false, args.max_errors, false)?;
+
+    let mut emulator = Emulator::new();
+    emulator.stdout_port = args.stdout;
+    emulator.stdin_port = args.stdin;
+    emulator.load(generator.start_point, &context.target)?;
+
+    if args.stdin.is_some() {
+        let mut input = Vec::new();
+        std::io::stdin().read_to_end(&mut input)?;
+        emulator.input.extend(input);
+    }
+
+    let entry = match &args.entry {
+        Some(entry) => resolve_address(&generator, entry)?,
+        None => generator.start_point as u16
+    };
+
+    let result = emulator.call(entry, args.max_cycles);
+    std::io::stdout().write_all(&emulator.output)?;
+    if emulator.output.last().is_some_and(|byte| *byte != b'\n') {
+        println!();
+    }
+
+    let reason = result?;
+    if !args.silent {
+        match reason {
+            StopReason::Break => info!("Stopped with BRK at ${:04X}", emulator.pc),
+            StopReason::Loop => info!("Stopped with endless loop at ${:04X}", emulator.pc),
+            StopReason::Return => info!("Stopped with RTS")
+        };
+    }
+
+    println!("{}", emulator.registers_text());
+    for range in args.dump.iter() {
+        print!("{}", emulator.memory_text(*range.start() as u16, (*range.end()).min(0xffff) as u16));
+    }
+    Ok(())
+}
+
+fn execute_test(args: &TestArgs) -> Result<(), StarterError> {
+    let (generator, context) = assemble(&args.source, &args.defines, CpuType::Mos6502, args.silent, false, args.max_errors, false)?;
+
+    let lines = match &args.spec {
+        Some(spec) => file_spec(&spec.display().to_string(), &String::from_utf8_lossy(&read_file(spec.clone())?)),
+        None => embedded_spec(&context)
+    };
+
+    let tests = parse_spec(&lines, &generator, &context)?;
+    if tests.is_empty() {
+        return Err(TestSpecError::NoTest.into());
+    }
+
+    let mut failed = 0;
+    for test in tests.iter() {
+        let result = run_test(test, &context.target, generator.start_point, args.max_cycles);
+        match result.passed() {
+            true => println!("PASS {} ({} cycles)", result.name, result.cycles),
+            false => {
+                failed += 1;
+                println!("FAIL {}", result.name);
+                for failure in result.failures.iter() {
+                    println!("    {}", failure);
+                }
+            }
+        };
+    }
+
+    println!("{} tests, {} passed, {} failed", tests.len(), tests.len() - failed, failed);
+    match failed {
+        0 => Ok(()),
+        _ => Err(StarterError::TestFailed(failed, tests.len()))
+    }
+}
+
+fn execute(cli: &Cli) -> Result<(), StarterError> {
+    match &cli.command {
+        Some(Command::Disasm(args)) => return execute_disasm(args),
+        Some(Command::Run(args)) => return execute_run(args),
+        Some(Command::Test(args)) => return execute_test(args),
+        None => ()
+    };
+
+    let source = match &cli.source {
+        Some(source) => source,
+        None => return Err(StarterError::InvalidArgument)
+    };
+
+    if !cli.binary_dump && !cli.token_dump && cli.target.is_none() {
+        return Err(StarterError::InvalidArgument);
+    }
+
+    if !cli.silent {
+        info!("timu6502asm Compiler");
+    }
+
+    let (generator, context) = assemble(source, &cli.defines, cli.cpu, cli.silent, cli.token_dump, cli.max_errors, cli.relax_branches)?;
 
     if cli.binary_dump {
         generator.dump(&context); 
     }
 
     if let Some(target) = &cli.target {
+        let output = generate_output(cli.format, &generator, &context, cli.basic_stub)?;
         let mut file = File::create(target)?;
-        file.write_all(&context.target)?;
+        file.write_all(&output)?;
+    }
+
+    if let Some(listing) = &cli.listing {
+        let mut file = File::create(listing)?;
+        file.write_all(generate_listing(&generator, &context).as_bytes())?;
+    }
+
+    if let Some(symbols) = &cli.symbols {
+        for (path, content) in generate_symbols(cli.symbol_format, symbols, &generator, &context) {
+            let mut file = File::create(path)?;
+            file.write_all(content.as_bytes())?;
+        }
     }
 
     if !cli.silent {
@@ -130,9 +456,22 @@ fn main() {
 
     let cli: Cli = Cli::parse();
 
+    let silent = match &cli.command {
+        Some(Command::Run(args)) => args.silent,
+        Some(Command::Test(args)) => args.silent,
+        _ => cli.silent
+    };
+
     if let Err(error) = execute(&cli) {
-        if !cli.silent {
-            error!("Compilation failed.");
+        if !silent {
+            /* Assembly succeeded if the program or the tests could not be run */
+            let message = match (&error, &cli.command) {
+                (StarterError::TestFailed(..) | StarterError::TestSpec(_), _) => "Tests failed.",
+                (StarterError::Emulator(_) | StarterError::UnknownAddress(_), _) => "Execution failed.",
+                (_, Some(Command::Disasm(_))) => "Disassembly failed.",
+                _ => "Compilation failed."
+            };
+            error!("{}", message);
             error!("Reason: {}", error);
         }
         
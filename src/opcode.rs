@@ -12,86 +12,315 @@ pub enum ModeType {
     AbsoluteY,
     IndirectX,
     IndirectY,
-    Indirect
+    Indirect,
+    ZeroPageIndirect,
+    AbsoluteIndirectX,
+    ZeroPageRelative,
+    AbsoluteLong,
+    AbsoluteLongX,
+    DirectIndirectLong,
+    DirectIndirectLongY,
+    AbsoluteIndirectLong,
+    StackRelative,
+    StackRelativeIndirectY,
+    RelativeLong,
+    BlockMove
+}
+
+/* Every addressing mode has a CPU mask, the mode is available if the selected CPU's flag is in the mask */
+pub const CPU_6502: u8 = 0b0000_0001;
+pub const CPU_6502X: u8 = 0b0000_0010;
+pub const CPU_65C02: u8 = 0b0000_0100;
+pub const CPU_R65C02: u8 = 0b0000_1000;
+pub const CPU_W65C02: u8 = 0b0001_0000;
+pub const CPU_65816: u8 = 0b0010_0000;
+
+pub const ALL_CPUS: u8 = 0b1111_1111;
+pub const CMOS_CPUS: u8 = CPU_65C02 | CPU_R65C02 | CPU_W65C02 | CPU_65816;
+pub const ROCKWELL_CPUS: u8 = CPU_R65C02 | CPU_W65C02;
+pub const WDC_CPUS: u8 = CPU_W65C02 | CPU_65816;
+pub const ILLEGAL_CPUS: u8 = CPU_6502X;
+pub const W65816_CPUS: u8 = CPU_65816;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum CpuType {
+    #[default]
+    Mos6502,
+    Mos6502X,
+    Cmos65C02,
+    Rockwell65C02,
+    Wdc65C02,
+    Wdc65816
+}
+
+pub const CPU_NAMES: [(&str, CpuType); 6] = [("6502", CpuType::Mos6502), ("6502X", CpuType::Mos6502X), ("65C02", CpuType::Cmos65C02), ("R65C02", CpuType::Rockwell65C02), ("W65C02", CpuType::Wdc65C02), ("65816", CpuType::Wdc65816)];
+
+impl CpuType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_uppercase();
+        CPU_NAMES.iter().find(|(cpu_name, _)| *cpu_name == name).map(|(_, cpu)| *cpu)
+    }
+
+    pub fn name(&self) -> &'static str {
+        CPU_NAMES.iter().find(|(_, cpu)| cpu == self).map(|(name, _)| *name).unwrap_or_default()
+    }
+
+    pub fn flag(&self) -> u8 {
+        match self {
+            CpuType::Mos6502 => CPU_6502,
+            CpuType::Mos6502X => CPU_6502X,
+            CpuType::Cmos65C02 => CPU_65C02,
+            CpuType::Rockwell65C02 => CPU_R65C02,
+            CpuType::Wdc65C02 => CPU_W65C02,
+            CpuType::Wdc65816 => CPU_65816
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct ModeInfo {
     pub mode: ModeType,
-    pub opcode: u8
+    pub opcode: u8,
+    pub cpus: u8
 }
 
-pub const INSTS: [&[u8; 3]; 56] = [
-    b"ADC", b"AND", b"ASL", b"BCC", b"BCS", b"BEQ", b"BIT", b"BMI", b"BNE", b"BPL", b"BRK", b"BVC", b"BVS",
-    b"CLC", b"CLD", b"CLI", b"CLV", b"CMP", b"CPX", b"CPY", b"DEC", b"DEX", b"DEY", b"EOR", b"INC", b"INX",
-    b"INY", b"JMP", b"JSR", b"LDA", b"LDX", b"LDY", b"LSR", b"NOP", b"ORA", b"PHA", b"PHP", b"PLA", b"PLP",
-    b"ROL", b"ROR", b"RTI", b"RTS", b"SBC", b"SEC", b"SED", b"SEI", b"STA", b"STX", b"STY", b"TAX", b"TAY",
-    b"TSX", b"TXA", b"TXS", b"TYA",
+pub const INSTS: [&str; 154] = [
+    "ADC", "AND", "ASL", "BCC", "BCS", "BEQ", "BIT", "BMI", "BNE", "BPL", "BRK", "BVC", "BVS",
+    "CLC", "CLD", "CLI", "CLV", "CMP", "CPX", "CPY", "DEC", "DEX", "DEY", "EOR", "INC", "INX",
+    "INY", "JMP", "JSR", "LDA", "LDX", "LDY", "LSR", "NOP", "ORA", "PHA", "PHP", "PLA", "PLP",
+    "ROL", "ROR", "RTI", "RTS", "SBC", "SEC", "SED", "SEI", "STA", "STX", "STY", "TAX", "TAY",
+    "TSX", "TXA", "TXS", "TYA", "BRA", "PHX", "PHY", "PLX", "PLY", "STZ", "TRB", "TSB", "BBR0",
+    "BBR1", "BBR2", "BBR3", "BBR4", "BBR5", "BBR6", "BBR7", "BBS0", "BBS1", "BBS2", "BBS3", "BBS4", "BBS5",
+    "BBS6", "BBS7", "RMB0", "RMB1", "RMB2", "RMB3", "RMB4", "RMB5", "RMB6", "RMB7", "SMB0", "SMB1", "SMB2",
+    "SMB3", "SMB4", "SMB5", "SMB6", "SMB7", "STP", "WAI", "SLO", "RLA", "SRE", "RRA", "SAX", "LAX",
+    "DCP", "ISC", "ANC", "ALR", "ARR", "SBX", "XAA", "LAS", "TAS", "SHA", "SHX", "SHY", "JAM",
+    "ASO", "LSE", "DCM", "ISB", "ASR", "AXS", "ANE", "LAR", "SHS", "AHX", "KIL", "BRL", "COP",
+    "JML", "JSL", "MVN", "MVP", "PEA", "PEI", "PER", "PHB", "PHD", "PHK", "PLB", "PLD", "REP",
+    "SEP", "RTL", "TCD", "TCS", "TDC", "TSC", "TXY", "TYX", "WDM", "XBA", "XCE",
 ];
 
-pub const INSTS_SIZE: [u8; 56] = [2, 2, 1, 2, 2, 2, 2, 2, 2, 2, 1, 2, 2, 1, 1, 1, 1, 2, 2, 2, 2, 1, 1, 2, 2, 1, 1, 3, 3, 2, 2, 2, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1];
-
-#[allow(unused_variables)]
-pub const INSTR_NAMES: [&str; 56] = ["ADC", "AND", "ASL", "BCC", "BCS", "BEQ", "BIT", "BMI", "BNE", "BPL", "BRK", "BVC", "BVS", "CLC", "CLD", "CLI", "CLV", "CMP", "CPX", "CPY", "DEC", "DEX", "DEY", "EOR", "INC", "INX", "INY", "JMP", "JSR", "LDA", "LDX", "LDY", "LSR", "NOP", "ORA", "PHA", "PHP", "PLA", "PLP", "ROL", "ROR", "RTI", "RTS", "SBC", "SEC", "SED", "SEI", "STA", "STX", "STY", "TAX", "TAY", "TSX", "TXA", "TXS", "TYA"];
-
-pub const ADC_MODES: [ModeInfo; 8] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x69}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x65}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x75}, ModeInfo { mode: ModeType::Absolute, opcode: 0x6D}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x7D}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x79}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x61}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x71}];
-pub const AND_MODES: [ModeInfo; 8] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x29}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x25}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x35}, ModeInfo { mode: ModeType::Absolute, opcode: 0x2D}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x3D}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x39}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x21}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x31}];
-pub const ASL_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x0A}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x06}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x16}, ModeInfo { mode: ModeType::Absolute, opcode: 0x0E}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x1E}];
-pub const BCC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x90}];
-pub const BCS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0xB0}];
-pub const BEQ_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0xF0}];
-pub const BIT_MODES: [ModeInfo; 2] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x24}, ModeInfo { mode: ModeType::Absolute, opcode: 0x2C}];
-pub const BMI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x30}];
-pub const BNE_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0xD0}];
-pub const BPL_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x10}];
-pub const BRK_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x00}];
-pub const BVC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x50}];
-pub const BVS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x70}];
-pub const CLC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x18}];
-pub const CLD_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xD8}];
-pub const CLI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x58}];
-pub const CLV_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xB8}];
-pub const CMP_MODES: [ModeInfo; 8] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xC9}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC5}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xD5}, ModeInfo { mode: ModeType::Absolute, opcode: 0xCD}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xDD}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xD9}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xC1}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xD1}];
-pub const CPX_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xE0}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE4}, ModeInfo { mode: ModeType::Absolute, opcode: 0xEC}];
-pub const CPY_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xC0}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC4}, ModeInfo { mode: ModeType::Absolute, opcode: 0xCC}];
-pub const DEC_MODES: [ModeInfo; 4] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC6}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xD6}, ModeInfo { mode: ModeType::Absolute, opcode: 0xCE}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xDE}];
-pub const DEX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xCA}];
-pub const DEY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x88}];
-pub const EOR_MODES: [ModeInfo; 8] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x49}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x45}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x55}, ModeInfo { mode: ModeType::Absolute, opcode: 0x4D}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x5D}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x59}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x41}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x51}];
-pub const INC_MODES: [ModeInfo; 4] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE6}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xF6}, ModeInfo { mode: ModeType::Absolute, opcode: 0xEE}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xFE}];
-pub const INX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xE8}];
-pub const INY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xC8}];
-pub const JMP_MODES: [ModeInfo; 2] = [ModeInfo { mode: ModeType::Absolute, opcode: 0x4C}, ModeInfo { mode: ModeType::Indirect, opcode: 0x6C}];
-pub const JSR_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Absolute, opcode: 0x20}];
-pub const LDA_MODES: [ModeInfo; 8] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xA9}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA5}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xB5}, ModeInfo { mode: ModeType::Absolute, opcode: 0xAD}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xBD}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xB9}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xA1}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xB1}];
-pub const LDX_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xA2}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA6}, ModeInfo { mode: ModeType::ZeroPageY, opcode: 0xB6}, ModeInfo { mode: ModeType::Absolute, opcode: 0xAE}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xBE}];
-pub const LDY_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xA0}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA4}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xB4}, ModeInfo { mode: ModeType::Absolute, opcode: 0xAC}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xBC}];
-pub const LSR_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x4A}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x46}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x56}, ModeInfo { mode: ModeType::Absolute, opcode: 0x4E}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x5E}];
-pub const NOP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xEA}];
-pub const ORA_MODES: [ModeInfo; 8] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x09}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x05}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x15}, ModeInfo { mode: ModeType::Absolute, opcode: 0x0D}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x1D}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x19}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x01}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x11}];
-pub const PHA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x48}];
-pub const PHP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x08}];
-pub const PLA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x68}];
-pub const PLP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x28}];
-pub const ROL_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x2A}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x26}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x36}, ModeInfo { mode: ModeType::Absolute, opcode: 0x2E}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x3E}];
-pub const ROR_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x6A}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x66}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x76}, ModeInfo { mode: ModeType::Absolute, opcode: 0x6E}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x7E}];
-pub const RTI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x40}];
-pub const RTS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x60}];
-pub const SBC_MODES: [ModeInfo; 8] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xE9}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE5}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xF5}, ModeInfo { mode: ModeType::Absolute, opcode: 0xED}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xFD}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xF9}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xE1}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xF1}];
-pub const SEC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x38}];
-pub const SED_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xF8}];
-pub const SEI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x78}];
-pub const STA_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x85}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x95}, ModeInfo { mode: ModeType::Absolute, opcode: 0x8D}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x9D}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x99}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x81}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x91}];
-pub const STX_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x86}, ModeInfo { mode: ModeType::ZeroPageY, opcode: 0x96}, ModeInfo { mode: ModeType::Absolute, opcode: 0x8E}];
-pub const STY_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x84}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x94}, ModeInfo { mode: ModeType::Absolute, opcode: 0x8C}];
-pub const TAX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xAA}];
-pub const TAY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xA8}];
-pub const TSX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xBA}];
-pub const TXA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x8A}];
-pub const TXS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x9A}];
-pub const TYA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x98}];
-
-
-pub const MODES: [&[ModeInfo]; 56] = [&ADC_MODES, &AND_MODES, &ASL_MODES, &BCC_MODES, &BCS_MODES, &BEQ_MODES, &BIT_MODES, &BMI_MODES, &BNE_MODES, &BPL_MODES, &BRK_MODES, &BVC_MODES, &BVS_MODES, &CLC_MODES, &CLD_MODES, &CLI_MODES, &CLV_MODES, &CMP_MODES, &CPX_MODES, &CPY_MODES, &DEC_MODES, &DEX_MODES, &DEY_MODES, &EOR_MODES, &INC_MODES, &INX_MODES, &INY_MODES, &JMP_MODES, &JSR_MODES, &LDA_MODES, &LDX_MODES, &LDY_MODES, &LSR_MODES, &NOP_MODES, &ORA_MODES, &PHA_MODES, &PHP_MODES, &PLA_MODES, &PLP_MODES, &ROL_MODES, &ROR_MODES, &RTI_MODES, &RTS_MODES, &SBC_MODES, &SEC_MODES, &SED_MODES, &SEI_MODES, &STA_MODES, &STX_MODES, &STY_MODES, &TAX_MODES, &TAY_MODES, &TSX_MODES, &TXA_MODES, &TXS_MODES, &TYA_MODES];
-pub const BRANCH_INSTS: [usize; 8] = [3, 4, 5, 7, 8, 9, 11, 12];
-pub const JUMP_INSTS: [usize; 2] = [27, 28];
\ No newline at end of file
+pub const ADC_MODES: [ModeInfo; 15] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x69, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x65, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x75, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x6D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x7D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x79, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x61, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x71, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0x72, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x6F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0x7F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0x67, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0x77, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0x63, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0x73, cpus: W65816_CPUS}];
+pub const AND_MODES: [ModeInfo; 15] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x29, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x25, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x35, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x2D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x3D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x39, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x21, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x31, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0x32, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x2F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0x3F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0x27, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0x37, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0x23, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0x33, cpus: W65816_CPUS}];
+pub const ASL_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x0A, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x06, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x16, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x0E, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x1E, cpus: ALL_CPUS}];
+pub const BCC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x90, cpus: ALL_CPUS}];
+pub const BCS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0xB0, cpus: ALL_CPUS}];
+pub const BEQ_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0xF0, cpus: ALL_CPUS}];
+pub const BIT_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x24, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x2C, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Immediate, opcode: 0x89, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x34, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x3C, cpus: CMOS_CPUS}];
+pub const BMI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x30, cpus: ALL_CPUS}];
+pub const BNE_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0xD0, cpus: ALL_CPUS}];
+pub const BPL_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x10, cpus: ALL_CPUS}];
+pub const BRK_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x00, cpus: ALL_CPUS}];
+pub const BVC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x50, cpus: ALL_CPUS}];
+pub const BVS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x70, cpus: ALL_CPUS}];
+pub const CLC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x18, cpus: ALL_CPUS}];
+pub const CLD_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xD8, cpus: ALL_CPUS}];
+pub const CLI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x58, cpus: ALL_CPUS}];
+pub const CLV_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xB8, cpus: ALL_CPUS}];
+pub const CMP_MODES: [ModeInfo; 15] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xC9, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC5, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xD5, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xCD, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xDD, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xD9, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xC1, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xD1, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0xD2, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0xCF, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0xDF, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0xC7, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0xD7, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0xC3, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0xD3, cpus: W65816_CPUS}];
+pub const CPX_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xE0, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE4, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xEC, cpus: ALL_CPUS}];
+pub const CPY_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xC0, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC4, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xCC, cpus: ALL_CPUS}];
+pub const DEC_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC6, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xD6, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xCE, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xDE, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Accumulator, opcode: 0x3A, cpus: CMOS_CPUS}];
+pub const DEX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xCA, cpus: ALL_CPUS}];
+pub const DEY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x88, cpus: ALL_CPUS}];
+pub const EOR_MODES: [ModeInfo; 15] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x49, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x45, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x55, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x4D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x5D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x59, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x41, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x51, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0x52, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x4F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0x5F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0x47, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0x57, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0x43, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0x53, cpus: W65816_CPUS}];
+pub const INC_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE6, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xF6, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xEE, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xFE, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Accumulator, opcode: 0x1A, cpus: CMOS_CPUS}];
+pub const INX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xE8, cpus: ALL_CPUS}];
+pub const INY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xC8, cpus: ALL_CPUS}];
+pub const JMP_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Absolute, opcode: 0x4C, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Indirect, opcode: 0x6C, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteIndirectX, opcode: 0x7C, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x5C, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteIndirectLong, opcode: 0xDC, cpus: W65816_CPUS}];
+pub const JSR_MODES: [ModeInfo; 2] = [ModeInfo { mode: ModeType::Absolute, opcode: 0x20, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteIndirectX, opcode: 0xFC, cpus: W65816_CPUS}];
+pub const LDA_MODES: [ModeInfo; 15] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xA9, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA5, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xB5, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xAD, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xBD, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xB9, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xA1, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xB1, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0xB2, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0xAF, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0xBF, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0xA7, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0xB7, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0xA3, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0xB3, cpus: W65816_CPUS}];
+pub const LDX_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xA2, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA6, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageY, opcode: 0xB6, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xAE, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xBE, cpus: ALL_CPUS}];
+pub const LDY_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xA0, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA4, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xB4, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xAC, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xBC, cpus: ALL_CPUS}];
+pub const LSR_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x4A, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x46, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x56, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x4E, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x5E, cpus: ALL_CPUS}];
+pub const NOP_MODES: [ModeInfo; 6] = [ModeInfo { mode: ModeType::Implied, opcode: 0xEA, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Immediate, opcode: 0x80, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x04, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x14, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x0C, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x1C, cpus: ILLEGAL_CPUS}];
+pub const ORA_MODES: [ModeInfo; 15] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x09, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x05, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x15, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x0D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x1D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x19, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x01, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x11, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0x12, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x0F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0x1F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0x07, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0x17, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0x03, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0x13, cpus: W65816_CPUS}];
+pub const PHA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x48, cpus: ALL_CPUS}];
+pub const PHP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x08, cpus: ALL_CPUS}];
+pub const PLA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x68, cpus: ALL_CPUS}];
+pub const PLP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x28, cpus: ALL_CPUS}];
+pub const ROL_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x2A, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x26, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x36, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x2E, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x3E, cpus: ALL_CPUS}];
+pub const ROR_MODES: [ModeInfo; 5] = [ModeInfo { mode: ModeType::Accumulator, opcode: 0x6A, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0x66, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x76, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x6E, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x7E, cpus: ALL_CPUS}];
+pub const RTI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x40, cpus: ALL_CPUS}];
+pub const RTS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x60, cpus: ALL_CPUS}];
+pub const SBC_MODES: [ModeInfo; 15] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xE9, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE5, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xF5, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xED, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xFD, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xF9, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xE1, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xF1, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0xF2, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0xEF, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0xFF, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0xE7, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0xF7, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0xE3, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0xF3, cpus: W65816_CPUS}];
+pub const SEC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x38, cpus: ALL_CPUS}];
+pub const SED_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xF8, cpus: ALL_CPUS}];
+pub const SEI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x78, cpus: ALL_CPUS}];
+pub const STA_MODES: [ModeInfo; 14] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x85, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x95, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x8D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x9D, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x99, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x81, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x91, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0x92, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x8F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteLongX, opcode: 0x9F, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLong, opcode: 0x87, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::DirectIndirectLongY, opcode: 0x97, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelative, opcode: 0x83, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::StackRelativeIndirectY, opcode: 0x93, cpus: W65816_CPUS}];
+pub const STX_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x86, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageY, opcode: 0x96, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x8E, cpus: ALL_CPUS}];
+pub const STY_MODES: [ModeInfo; 3] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x84, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x94, cpus: ALL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x8C, cpus: ALL_CPUS}];
+pub const TAX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xAA, cpus: ALL_CPUS}];
+pub const TAY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xA8, cpus: ALL_CPUS}];
+pub const TSX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xBA, cpus: ALL_CPUS}];
+pub const TXA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x8A, cpus: ALL_CPUS}];
+pub const TXS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x9A, cpus: ALL_CPUS}];
+pub const TYA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x98, cpus: ALL_CPUS}];
+pub const BRA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Relative, opcode: 0x80, cpus: CMOS_CPUS}];
+pub const PHX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xDA, cpus: CMOS_CPUS}];
+pub const PHY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x5A, cpus: CMOS_CPUS}];
+pub const PLX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xFA, cpus: CMOS_CPUS}];
+pub const PLY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x7A, cpus: CMOS_CPUS}];
+pub const STZ_MODES: [ModeInfo; 4] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x64, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x74, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x9C, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x9E, cpus: CMOS_CPUS}];
+pub const TRB_MODES: [ModeInfo; 2] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x14, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x1C, cpus: CMOS_CPUS}];
+pub const TSB_MODES: [ModeInfo; 2] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x04, cpus: CMOS_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x0C, cpus: CMOS_CPUS}];
+pub const BBR0_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x0F, cpus: ROCKWELL_CPUS}];
+pub const BBR1_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x1F, cpus: ROCKWELL_CPUS}];
+pub const BBR2_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x2F, cpus: ROCKWELL_CPUS}];
+pub const BBR3_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x3F, cpus: ROCKWELL_CPUS}];
+pub const BBR4_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x4F, cpus: ROCKWELL_CPUS}];
+pub const BBR5_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x5F, cpus: ROCKWELL_CPUS}];
+pub const BBR6_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x6F, cpus: ROCKWELL_CPUS}];
+pub const BBR7_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x7F, cpus: ROCKWELL_CPUS}];
+pub const BBS0_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x8F, cpus: ROCKWELL_CPUS}];
+pub const BBS1_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0x9F, cpus: ROCKWELL_CPUS}];
+pub const BBS2_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0xAF, cpus: ROCKWELL_CPUS}];
+pub const BBS3_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0xBF, cpus: ROCKWELL_CPUS}];
+pub const BBS4_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0xCF, cpus: ROCKWELL_CPUS}];
+pub const BBS5_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0xDF, cpus: ROCKWELL_CPUS}];
+pub const BBS6_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0xEF, cpus: ROCKWELL_CPUS}];
+pub const BBS7_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageRelative, opcode: 0xFF, cpus: ROCKWELL_CPUS}];
+pub const RMB0_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x07, cpus: ROCKWELL_CPUS}];
+pub const RMB1_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x17, cpus: ROCKWELL_CPUS}];
+pub const RMB2_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x27, cpus: ROCKWELL_CPUS}];
+pub const RMB3_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x37, cpus: ROCKWELL_CPUS}];
+pub const RMB4_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x47, cpus: ROCKWELL_CPUS}];
+pub const RMB5_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x57, cpus: ROCKWELL_CPUS}];
+pub const RMB6_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x67, cpus: ROCKWELL_CPUS}];
+pub const RMB7_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x77, cpus: ROCKWELL_CPUS}];
+pub const SMB0_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x87, cpus: ROCKWELL_CPUS}];
+pub const SMB1_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x97, cpus: ROCKWELL_CPUS}];
+pub const SMB2_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA7, cpus: ROCKWELL_CPUS}];
+pub const SMB3_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xB7, cpus: ROCKWELL_CPUS}];
+pub const SMB4_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC7, cpus: ROCKWELL_CPUS}];
+pub const SMB5_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xD7, cpus: ROCKWELL_CPUS}];
+pub const SMB6_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE7, cpus: ROCKWELL_CPUS}];
+pub const SMB7_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xF7, cpus: ROCKWELL_CPUS}];
+pub const STP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xDB, cpus: WDC_CPUS}];
+pub const WAI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xCB, cpus: WDC_CPUS}];
+
+pub const SLO_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x07, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x17, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x0F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x1F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x1B, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x03, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x13, cpus: ILLEGAL_CPUS}];
+pub const RLA_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x27, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x37, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x2F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x3F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x3B, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x23, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x33, cpus: ILLEGAL_CPUS}];
+pub const SRE_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x47, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x57, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x4F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x5F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x5B, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x43, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x53, cpus: ILLEGAL_CPUS}];
+pub const RRA_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x67, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0x77, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x6F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x7F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x7B, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x63, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x73, cpus: ILLEGAL_CPUS}];
+pub const SAX_MODES: [ModeInfo; 4] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0x87, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageY, opcode: 0x97, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0x8F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0x83, cpus: ILLEGAL_CPUS}];
+pub const LAX_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xAB, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPage, opcode: 0xA7, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageY, opcode: 0xB7, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xAF, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xBF, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xA3, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xB3, cpus: ILLEGAL_CPUS}];
+pub const DCP_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xC7, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xD7, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xCF, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xDF, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xDB, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xC3, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xD3, cpus: ILLEGAL_CPUS}];
+pub const ISC_MODES: [ModeInfo; 7] = [ModeInfo { mode: ModeType::ZeroPage, opcode: 0xE7, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::ZeroPageX, opcode: 0xF7, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::Absolute, opcode: 0xEF, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteX, opcode: 0xFF, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xFB, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectX, opcode: 0xE3, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0xF3, cpus: ILLEGAL_CPUS}];
+pub const ANC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x0B, cpus: ILLEGAL_CPUS}];
+pub const ALR_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x4B, cpus: ILLEGAL_CPUS}];
+pub const ARR_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x6B, cpus: ILLEGAL_CPUS}];
+pub const SBX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xCB, cpus: ILLEGAL_CPUS}];
+pub const XAA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x8B, cpus: ILLEGAL_CPUS}];
+pub const LAS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::AbsoluteY, opcode: 0xBB, cpus: ILLEGAL_CPUS}];
+pub const TAS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x9B, cpus: ILLEGAL_CPUS}];
+pub const SHA_MODES: [ModeInfo; 2] = [ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x9F, cpus: ILLEGAL_CPUS}, ModeInfo { mode: ModeType::IndirectY, opcode: 0x93, cpus: ILLEGAL_CPUS}];
+pub const SHX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::AbsoluteY, opcode: 0x9E, cpus: ILLEGAL_CPUS}];
+pub const SHY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::AbsoluteX, opcode: 0x9C, cpus: ILLEGAL_CPUS}];
+pub const JAM_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x02, cpus: ILLEGAL_CPUS}];
+pub const BRL_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::RelativeLong, opcode: 0x82, cpus: W65816_CPUS}];
+pub const COP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x02, cpus: W65816_CPUS}];
+pub const JML_MODES: [ModeInfo; 2] = [ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x5C, cpus: W65816_CPUS}, ModeInfo { mode: ModeType::AbsoluteIndirectLong, opcode: 0xDC, cpus: W65816_CPUS}];
+pub const JSL_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::AbsoluteLong, opcode: 0x22, cpus: W65816_CPUS}];
+pub const MVN_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::BlockMove, opcode: 0x54, cpus: W65816_CPUS}];
+pub const MVP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::BlockMove, opcode: 0x44, cpus: W65816_CPUS}];
+pub const PEA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Absolute, opcode: 0xF4, cpus: W65816_CPUS}];
+pub const PEI_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::ZeroPageIndirect, opcode: 0xD4, cpus: W65816_CPUS}];
+pub const PER_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::RelativeLong, opcode: 0x62, cpus: W65816_CPUS}];
+pub const PHB_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x8B, cpus: W65816_CPUS}];
+pub const PHD_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x0B, cpus: W65816_CPUS}];
+pub const PHK_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x4B, cpus: W65816_CPUS}];
+pub const PLB_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xAB, cpus: W65816_CPUS}];
+pub const PLD_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x2B, cpus: W65816_CPUS}];
+pub const REP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xC2, cpus: W65816_CPUS}];
+pub const SEP_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0xE2, cpus: W65816_CPUS}];
+pub const RTL_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x6B, cpus: W65816_CPUS}];
+pub const TCD_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x5B, cpus: W65816_CPUS}];
+pub const TCS_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x1B, cpus: W65816_CPUS}];
+pub const TDC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x7B, cpus: W65816_CPUS}];
+pub const TSC_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x3B, cpus: W65816_CPUS}];
+pub const TXY_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0x9B, cpus: W65816_CPUS}];
+pub const TYX_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xBB, cpus: W65816_CPUS}];
+pub const WDM_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Immediate, opcode: 0x42, cpus: W65816_CPUS}];
+pub const XBA_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xEB, cpus: W65816_CPUS}];
+pub const XCE_MODES: [ModeInfo; 1] = [ModeInfo { mode: ModeType::Implied, opcode: 0xFB, cpus: W65816_CPUS}];
+
+pub const MODES: [&[ModeInfo]; 154] = [&ADC_MODES, &AND_MODES, &ASL_MODES, &BCC_MODES, &BCS_MODES, &BEQ_MODES, &BIT_MODES, &BMI_MODES, &BNE_MODES, &BPL_MODES, &BRK_MODES, &BVC_MODES, &BVS_MODES, &CLC_MODES, &CLD_MODES, &CLI_MODES, &CLV_MODES, &CMP_MODES, &CPX_MODES, &CPY_MODES, &DEC_MODES, &DEX_MODES, &DEY_MODES, &EOR_MODES, &INC_MODES, &INX_MODES, &INY_MODES, &JMP_MODES, &JSR_MODES, &LDA_MODES, &LDX_MODES, &LDY_MODES, &LSR_MODES, &NOP_MODES, &ORA_MODES, &PHA_MODES, &PHP_MODES, &PLA_MODES, &PLP_MODES, &ROL_MODES, &ROR_MODES, &RTI_MODES, &RTS_MODES, &SBC_MODES, &SEC_MODES, &SED_MODES, &SEI_MODES, &STA_MODES, &STX_MODES, &STY_MODES, &TAX_MODES, &TAY_MODES, &TSX_MODES, &TXA_MODES, &TXS_MODES, &TYA_MODES, &BRA_MODES, &PHX_MODES, &PHY_MODES, &PLX_MODES, &PLY_MODES, &STZ_MODES, &TRB_MODES, &TSB_MODES, &BBR0_MODES, &BBR1_MODES, &BBR2_MODES, &BBR3_MODES, &BBR4_MODES, &BBR5_MODES, &BBR6_MODES, &BBR7_MODES, &BBS0_MODES, &BBS1_MODES, &BBS2_MODES, &BBS3_MODES, &BBS4_MODES, &BBS5_MODES, &BBS6_MODES, &BBS7_MODES, &RMB0_MODES, &RMB1_MODES, &RMB2_MODES, &RMB3_MODES, &RMB4_MODES, &RMB5_MODES, &RMB6_MODES, &RMB7_MODES, &SMB0_MODES, &SMB1_MODES, &SMB2_MODES, &SMB3_MODES, &SMB4_MODES, &SMB5_MODES, &SMB6_MODES, &SMB7_MODES, &STP_MODES, &WAI_MODES, &SLO_MODES, &RLA_MODES, &SRE_MODES, &RRA_MODES, &SAX_MODES, &LAX_MODES, &DCP_MODES, &ISC_MODES, &ANC_MODES, &ALR_MODES, &ARR_MODES, &SBX_MODES, &XAA_MODES, &LAS_MODES, &TAS_MODES, &SHA_MODES, &SHX_MODES, &SHY_MODES, &JAM_MODES, &SLO_MODES, &SRE_MODES, &DCP_MODES, &ISC_MODES, &ALR_MODES, &SBX_MODES, &XAA_MODES, &LAS_MODES, &TAS_MODES, &SHA_MODES, &JAM_MODES, &BRL_MODES, &COP_MODES, &JML_MODES, &JSL_MODES, &MVN_MODES, &MVP_MODES, &PEA_MODES, &PEI_MODES, &PER_MODES, &PHB_MODES, &PHD_MODES, &PHK_MODES, &PLB_MODES, &PLD_MODES, &REP_MODES, &SEP_MODES, &RTL_MODES, &TCD_MODES, &TCS_MODES, &TDC_MODES, &TSC_MODES, &TXY_MODES, &TYX_MODES, &WDM_MODES, &XBA_MODES, &XCE_MODES];
+
+pub fn has_mode(instr: usize, mode: ModeType) -> bool {
+    MODES[instr].iter().any(|info| info.mode == mode)
+}
+
+/* Branch instructions use relative address */
+pub fn is_branch(instr: usize) -> bool {
+    has_mode(instr, ModeType::Relative) || has_mode(instr, ModeType::RelativeLong)
+}
+
+/* Conditional branch with the opposite condition, used for long branches (ex. BNE far is BEQ *+5, JMP far) */
+pub fn inverse_branch(instr: usize) -> Option<usize> {
+    let inverse = match INSTS[instr] {
+        "BCC" => "BCS",
+        "BCS" => "BCC",
+        "BEQ" => "BNE",
+        "BNE" => "BEQ",
+        "BMI" => "BPL",
+        "BPL" => "BMI",
+        "BVC" => "BVS",
+        "BVS" => "BVC",
+        _ => return None
+    };
+    find_instr(inverse)
+}
+
+/* Mnemonics of the other CPUs are not instructions, they could be used as names (ex. 'lax = 5' for 6502) */
+pub fn is_available(instr: usize, cpu: CpuType) -> bool {
+    MODES[instr].iter().any(|info| info.cpus & cpu.flag() != 0)
+}
+
+pub fn find_instr(name: &str) -> Option<usize> {
+    INSTS.iter().position(|item| *item == name)
+}
+
+/* Bit branch (ex. BBR0 $10, label) and block move (ex. MVN $7E, $7F) instructions have two operands */
+pub fn has_two_operands(instr: usize) -> bool {
+    has_mode(instr, ModeType::ZeroPageRelative) || has_mode(instr, ModeType::BlockMove)
+}
+
+/* Immediate operand size of these instructions follows the M (accumulator) and X (index) flags of 65816 */
+pub fn is_accumulator_width(instr: usize) -> bool {
+    ["ADC", "AND", "BIT", "CMP", "EOR", "LDA", "ORA", "SBC"].contains(&INSTS[instr])
+}
+
+pub fn is_index_width(instr: usize) -> bool {
+    ["CPX", "CPY", "LDX", "LDY"].contains(&INSTS[instr])
+}
+
+/* Instruction can be used without operand (ex. ASL, INX) */
+pub fn has_implied(instr: usize) -> bool {
+    has_mode(instr, ModeType::Implied) || has_mode(instr, ModeType::Accumulator)
+}
+
+/* Operand size in bytes, immediate size is for 8 bit registers */
+pub fn operand_size(mode: ModeType) -> usize {
+    match mode {
+        ModeType::Implied | ModeType::Accumulator => 0,
+        ModeType::ZeroPage | ModeType::Relative | ModeType::Immediate | ModeType::ZeroPageX | ModeType::ZeroPageY | ModeType::IndirectX | ModeType::IndirectY |
+        ModeType::ZeroPageIndirect | ModeType::DirectIndirectLong | ModeType::DirectIndirectLongY | ModeType::StackRelative | ModeType::StackRelativeIndirectY => 1,
+        ModeType::Absolute | ModeType::AbsoluteX | ModeType::AbsoluteY | ModeType::Indirect | ModeType::AbsoluteIndirectX | ModeType::AbsoluteIndirectLong |
+        ModeType::ZeroPageRelative | ModeType::RelativeLong | ModeType::BlockMove => 2,
+        ModeType::AbsoluteLong | ModeType::AbsoluteLongX => 3
+    }
+}
+
+/* Instruction and mode of the opcode. Only the first opcode of an instruction and mode pair is decoded, the assembler generates the same opcode again */
+pub fn decode_opcode(opcode: u8, cpu: CpuType) -> Option<(usize, &'static ModeInfo)> {
+    let flag = cpu.flag();
+
+    MODES.iter().enumerate().find_map(|(instr, modes)| {
+        let info = modes.iter().find(|info| info.opcode == opcode && info.cpus & flag != 0)?;
+        let first = modes.iter().find(|item| item.mode == info.mode && item.cpus & flag != 0)?;
+        match first.opcode == opcode {
+            true => Some((instr, info)),
+            false => None
+        }
+    })
+}
@@ -0,0 +1,279 @@
+use std::{fmt::Write, ops::Range};
+
+use thiserror::Error;
+
+use crate::{code_gen::CodeGenerator, context::Context};
+
+pub const INES_PRG_BANK_SIZE: usize = 16 * 1024;
+pub const INES_CHR_BANK_SIZE: usize = 8 * 1024;
+
+const BASIC_SYS_TOKEN: u8 = 0x9e;
+const BASIC_LINE_NUMBER: u16 = 10;
+
+/* Maximum data bytes in a Intel HEX or SREC record */
+const RECORD_SIZE: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum OutputError {
+    #[error("'.inesprg' is not defined")]
+    MissingPrgSize,
+    #[error("{0} data is {1} bytes, but only {2} bytes are defined")]
+    BankOverflow(&'static str, usize, usize),
+    #[error("'{0}' value ({1}) is out of range")]
+    OutOfRange(&'static str, u32),
+    #[error("'{0}' value ({1}) is not a valid RAM size")]
+    InvalidRamSize(&'static str, u32),
+    #[error("Program start address 0x{0:04X} is not after the BASIC stub (0x{1:04X})")]
+    BasicStubOverlap(u32, u32),
+    #[error("BASIC stub is only supported by prg format")]
+    BasicStubNotSupported
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Raw binary
+    Binary,
+
+    /// iNES or NES 2.0 ROM with header
+    Ines,
+
+    /// Commodore program file with load address
+    Prg,
+
+    /// Intel HEX records
+    Ihex,
+
+    /// Motorola S-record
+    Srec
+}
+
+/* Header information from .ines* and .nes2* directives */
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct InesHeader {
+    pub prg_banks: Option<u32>,
+    pub chr_banks: u32,
+    pub mapper: u32,
+    pub mirroring: u32,
+    pub submapper: u32,
+    pub prg_ram: u32,
+    pub prg_nvram: u32,
+    pub chr_ram: u32,
+    pub chr_nvram: u32,
+    pub timing: u32,
+    pub nes2: bool
+}
+
+/* basic_start is the load address of the BASIC SYS stub, only for prg format */
+pub fn generate_output(format: OutputFormat, generator: &CodeGenerator, context: &Context, basic_start: Option<u16>) -> Result<Vec<u8>, OutputError> {
+    match (format, basic_start) {
+        (OutputFormat::Prg, _) => generate_prg(&context.target, generator.start_point, basic_start),
+        (_, Some(_)) => Err(OutputError::BasicStubNotSupported),
+        (OutputFormat::Binary, None) => Ok(context.target.clone()),
+        (OutputFormat::Ines, None) => generate_ines(&generator.ines, &context.target, generator.chr_start, generator.fillvalue),
+        (OutputFormat::Ihex, None) => Ok(generate_ihex(&context.target, generator.start_point, &generator.org_gaps)),
+        (OutputFormat::Srec, None) => Ok(generate_srec(&context.target, generator.start_point, &generator.org_gaps))
+    }
+}
+
+fn check_range(name: &'static str, value: u32, max: u32) -> Result<u32, OutputError> {
+    match value > max {
+        true => Err(OutputError::OutOfRange(name, value)),
+        false => Ok(value)
+    }
+}
+
+/* NES 2.0 RAM sizes are written as shift count, size is 64 << shift */
+fn ram_shift(name: &'static str, size: u32) -> Result<u8, OutputError> {
+    match size {
+        0 => Ok(0),
+        size if size.is_power_of_two() && (128..=(64 << 15)).contains(&size) => Ok((size.trailing_zeros() - 6) as u8),
+        size => Err(OutputError::InvalidRamSize(name, size))
+    }
+}
+
+/* 16 bytes header, PRG banks and CHR banks. Codes after .chr directive are CHR data */
+pub fn generate_ines(header: &InesHeader, data: &[u8], chr_start: Option<usize>, fillvalue: u8) -> Result<Vec<u8>, OutputError> {
+    let prg_banks = header.prg_banks.ok_or(OutputError::MissingPrgSize)?;
+    let nes2 = header.nes2 || header.mapper > 0xff || prg_banks > 0xff || header.chr_banks > 0xff;
+    let max_banks = if nes2 { 0xeff } else { 0xff };
+
+    let prg_banks = check_range("inesprg", prg_banks, max_banks)?;
+    let chr_banks = check_range("ineschr", header.chr_banks, max_banks)?;
+    let mapper = check_range("inesmap", header.mapper, 0xfff)?;
+    let mirroring = check_range("inesmir", header.mirroring, 0x0f)?;
+    let submapper = check_range("nes2sub", header.submapper, 0x0f)?;
+    let timing = check_range("nes2tv", header.timing, 0x03)?;
+
+    let (prg_data, chr_data) = data.split_at(chr_start.unwrap_or(data.len()).min(data.len()));
+    let prg_size = prg_banks as usize * INES_PRG_BANK_SIZE;
+    let chr_size = chr_banks as usize * INES_CHR_BANK_SIZE;
+
+    if prg_data.len() > prg_size {
+        return Err(OutputError::BankOverflow("PRG", prg_data.len(), prg_size));
+    }
+
+    if chr_data.len() > chr_size {
+        return Err(OutputError::BankOverflow("CHR", chr_data.len(), chr_size));
+    }
+
+    let mut output = Vec::with_capacity(16 + prg_size + chr_size);
+    output.extend_from_slice(b"NES\x1a");
+    output.push(prg_banks as u8);
+    output.push(chr_banks as u8);
+    output.push(((mapper & 0x0f) << 4) as u8 | mirroring as u8);
+
+    match nes2 {
+        true => {
+            output.push((mapper & 0xf0) as u8 | 0x08);
+            output.push((submapper << 4) as u8 | (mapper >> 8) as u8);
+            output.push(((chr_banks >> 8) << 4) as u8 | (prg_banks >> 8) as u8);
+            output.push(ram_shift("nes2bram", header.prg_nvram)? << 4 | ram_shift("nes2prgram", header.prg_ram)?);
+            output.push(ram_shift("nes2chrbram", header.chr_nvram)? << 4 | ram_shift("nes2chrram", header.chr_ram)?);
+            output.push(timing as u8);
+            output.extend_from_slice(&[0x00; 3]);
+        },
+        false => {
+            output.push((mapper & 0xf0) as u8);
+            output.extend_from_slice(&[0x00; 8]);
+        }
+    };
+
+    output.extend_from_slice(prg_data);
+    output.resize(16 + prg_size, fillvalue);
+    output.extend_from_slice(chr_data);
+    output.resize(16 + prg_size + chr_size, fillvalue);
+    Ok(output)
+}
+
+/* '10 SYS start' BASIC line, link to next line and zero line link as end of program */
+fn basic_stub(basic_start: u16, start: u32) -> Vec<u8> {
+    let digits = start.to_string();
+    let next_line = basic_start as usize + 2 + 2 + 1 + digits.len() + 1;
+
+    let mut stub = Vec::new();
+    stub.extend_from_slice(&(next_line as u16).to_le_bytes());
+    stub.extend_from_slice(&BASIC_LINE_NUMBER.to_le_bytes());
+    stub.push(BASIC_SYS_TOKEN);
+    stub.extend_from_slice(digits.as_bytes());
+    stub.push(0x00);
+    stub.extend_from_slice(&[0x00, 0x00]);
+    stub
+}
+
+/* Two bytes load address and the codes. With BASIC stub, the program is loaded at BASIC start and the codes are placed at .org address */
+pub fn generate_prg(data: &[u8], start_point: u32, basic_start: Option<u16>) -> Result<Vec<u8>, OutputError> {
+    let mut output = Vec::with_capacity(2 + data.len());
+
+    match basic_start {
+        Some(basic_start) => {
+            let stub = basic_stub(basic_start, start_point);
+            let stub_end = basic_start as u32 + stub.len() as u32;
+            if start_point < stub_end || start_point > 0xffff {
+                return Err(OutputError::BasicStubOverlap(start_point, stub_end));
+            }
+
+            output.extend_from_slice(&basic_start.to_le_bytes());
+            output.extend_from_slice(&stub);
+            output.resize(2 + (start_point - basic_start as u32) as usize, 0x00);
+        },
+        None => output.extend_from_slice(&(start_point as u16).to_le_bytes())
+    };
+
+    output.extend_from_slice(data);
+    Ok(output)
+}
+
+/* Data records with load addresses. .org gaps are skipped and a record does not cross a 64KB boundary */
+fn data_records<'a>(data: &'a [u8], start_point: u32, org_gaps: &[Range<usize>]) -> Vec<(u32, &'a [u8])> {
+    let mut records = Vec::new();
+    let mut position = 0;
+
+    for range in org_gaps.iter().cloned().chain(std::iter::once(data.len()..data.len())) {
+        let mut block = &data[position..range.start];
+        let mut address = start_point + position as u32;
+
+        while !block.is_empty() {
+            let size = block.len().min(RECORD_SIZE).min(0x10000 - (address & 0xffff) as usize);
+            let (record, rest) = block.split_at(size);
+            records.push((address, record));
+            address += size as u32;
+            block = rest;
+        }
+
+        position = range.end;
+    }
+
+    records
+}
+
+/* Count, address, type and data bytes. Checksum is two's complement of the sum of the bytes */
+fn ihex_record(output: &mut String, record_type: u8, address: u16, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8, (address >> 8) as u8, address as u8, record_type];
+    bytes.extend_from_slice(data);
+
+    let checksum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+    output.push(':');
+    for byte in bytes.iter().chain([checksum].iter()) {
+        let _ = write!(output, "{:02X}", byte);
+    }
+    output.push('\n');
+}
+
+/* Extended linear address records are used for the addresses above 64KB */
+pub fn generate_ihex(data: &[u8], start_point: u32, org_gaps: &[Range<usize>]) -> Vec<u8> {
+    let mut output = String::new();
+    let mut upper_address = 0;
+
+    for (address, record) in data_records(data, start_point, org_gaps) {
+        if address >> 16 != upper_address {
+            upper_address = address >> 16;
+            ihex_record(&mut output, 0x04, 0x0000, &(upper_address as u16).to_be_bytes());
+        }
+
+        ihex_record(&mut output, 0x00, address as u16, record);
+    }
+
+    match start_point > 0xffff {
+        true => ihex_record(&mut output, 0x05, 0x0000, &start_point.to_be_bytes()),
+        false => ihex_record(&mut output, 0x03, 0x0000, &[0x00, 0x00, (start_point >> 8) as u8, start_point as u8])
+    };
+
+    ihex_record(&mut output, 0x01, 0x0000, &[]);
+    output.into_bytes()
+}
+
+/* Count, address and data bytes. Checksum is one's complement of the sum of the bytes */
+fn srec_record(output: &mut String, record_type: u8, address: u32, address_size: usize, data: &[u8]) {
+    let mut bytes = vec![(address_size + data.len() + 1) as u8];
+    bytes.extend_from_slice(&address.to_be_bytes()[4 - address_size..]);
+    bytes.extend_from_slice(data);
+
+    let checksum = !bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    let _ = write!(output, "S{}", record_type);
+    for byte in bytes.iter().chain([checksum].iter()) {
+        let _ = write!(output, "{:02X}", byte);
+    }
+    output.push('\n');
+}
+
+/* S1 and S9 records for 16 bit addresses, S2 and S8 records for 24 bit addresses */
+pub fn generate_srec(data: &[u8], start_point: u32, org_gaps: &[Range<usize>]) -> Vec<u8> {
+    let records = data_records(data, start_point, org_gaps);
+    let long = start_point > 0xffff || records.iter().any(|(address, record)| address + record.len() as u32 > 0x10000);
+    let (address_size, data_type, start_type) = if long { (3, 2, 8) } else { (2, 1, 9) };
+
+    let mut output = String::new();
+    srec_record(&mut output, 0, 0x0000, 2, &[]);
+
+    for (address, record) in records.iter() {
+        srec_record(&mut output, data_type, *address, address_size, record);
+    }
+
+    match records.len() > 0xffff {
+        true => srec_record(&mut output, 6, records.len() as u32, 3, &[]),
+        false => srec_record(&mut output, 5, records.len() as u32, 2, &[])
+    };
+
+    srec_record(&mut output, start_type, start_point, address_size, &[]);
+    output.into_bytes()
+}
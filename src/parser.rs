@@ -1,7 +1,7 @@
 use core::str;
-use std::str::Utf8Error;
+use std::{rc::Rc, str::Utf8Error};
 
-use crate::{context::Context, opcode::INSTS, tool::{print_error, upper_case_byte}};
+use crate::{context::Context, opcode::INSTS, tool::{print_code_error, print_error}};
 use log::info;
 use strum_macros::EnumDiscriminants;
 use thiserror::Error;
@@ -33,7 +33,8 @@ pub struct Parser<'a> {
     size: usize,
     pub context: Context,
     pub data: &'a [u8],
-    pub file_id: usize
+    pub file_id: usize,
+    pub total_errors: usize
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -50,16 +51,61 @@ pub enum Token {
     Comma,
     OpenParenthesis,
     CloseParenthesis,
+    OpenSquareBracket,
+    CloseSquareBracket,
     Sharp,
+    Operator(Operator),
+    ProgramCounter,
     Branch(String),
     LocalBranch(String),
     Byte(u8),
     Word(u16),
+    Long(u32),
     NewLine(usize),
     Space(usize),
     End,
 }
 
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Operator {
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    And,
+    Or,
+    Xor,
+    Not,
+    ShiftLeft,
+    ShiftRight,
+    LessThan,
+    GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    LogicalAnd,
+    LogicalOr,
+    LogicalNot
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MacroCall {
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+    pub end: usize,
+    pub file_id: usize,
+    pub parent: Option<Rc<MacroCall>>
+}
+
+impl MacroCall {
+    pub fn depth(&self) -> usize {
+        1 + self.parent.as_ref().map(|parent| parent.depth()).unwrap_or_default()
+    }
+}
+
 #[derive(Debug)]
 #[derive(Clone)]
 pub struct TokenInfo {
@@ -67,7 +113,8 @@ pub struct TokenInfo {
     pub column: usize,
     pub token: Token,
     pub end: usize,
-    pub file_id: usize
+    pub file_id: usize,
+    pub macro_call: Option<Rc<MacroCall>>
 }
 
 #[derive(Debug, PartialEq, Error)]
@@ -115,7 +162,8 @@ impl<'a> Parser<'a> {
             size,
             context,
             data,
-            file_id
+            file_id,
+            total_errors: 0
         }
     }
 
@@ -125,7 +173,8 @@ impl<'a> Parser<'a> {
             column: self.column,
             end: self.end,
             token,
-            file_id: self.file_id
+            file_id: self.file_id,
+            macro_call: None
         });
     }
 
@@ -152,19 +201,50 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    /* Parsing continues from the next line after an error, the first error is returned */
     pub fn parse(&mut self) -> Result<(), ParseError> {
-        match self.inner_parse() {
-            Ok(_) => Ok(()),
-            Err(error) => {
-                if !self.context.silent {
-                    print_error(self.data, &error, self.line, self.column, self.end);
+        let mut first_error = None;
+
+        while let Err(error) = self.inner_parse() {
+            if self.context.add_error() && !self.context.silent {
+                let code_files = self.context.code_files.borrow();
+                match code_files.get(self.file_id) {
+                    Some(file) if !file.data.is_empty() => print_code_error(&code_files, self.file_id, &error, self.line, self.column, self.end),
+                    _ => print_error(self.data, &error, self.line, self.column, self.end)
+                };
+            }
+
+            self.total_errors += 1;
+            first_error.get_or_insert(error);
+
+            /* Tokens of the failed line are dropped, so the next stages do not report the same line */
+            {
+                let mut tokens = self.context.tokens.borrow_mut();
+                while !matches!(tokens.last().map(|item| &item.token), None | Some(Token::NewLine(_))) {
+                    tokens.pop();
                 }
+            }
 
-                Err(error)
+            if self.context.error_limit_reached() {
+                break;
             }
+            self.skip_line();
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(())
         }
     }
 
+    fn skip_line(&mut self) {
+        while self.index < self.size && !matches!(self.data[self.index], b'\r' | b'\n') {
+            self.index += 1;
+            self.end += 1;
+        }
+        self.column = self.end;
+    }
+
     fn peek(&mut self) -> Result<u8, ParseError> {
         self.empty_check()?;
         Ok(self.data[self.index])
@@ -217,8 +297,14 @@ impl<'a> Parser<'a> {
         let first = self.peek()?;
 
         match first {
-            b'$' => self.parse_hex(),
-            b'%' => self.parse_binary(),
+            b'$' => match self.peek2() {
+                Ok(n) if n.is_ascii_hexdigit() => self.parse_hex(),
+                _ => self.parse_program_counter()
+            },
+            b'%' => match self.last_token_is_value() {
+                true => self.parse_operator(),
+                false => self.parse_binary()
+            },
             b'0'..=b'9' => self.parse_absolute_decimal(),
             b'#' => self.parse_sharp(),
             b'@' => self.parse_local_branch(),
@@ -226,10 +312,20 @@ impl<'a> Parser<'a> {
             b'.' => self.parse_directive(),
             b'"' => self.parse_string(),
             b';' => self.parse_comment(),
-            b'=' => self.parse_assign(),
+            b'=' => match self.peek2() {
+                Ok(b'=') => self.parse_operator(),
+                _ => self.parse_assign()
+            },
             b'(' => self.parse_open_parenthesis(),
             b')' => self.parse_close_parenthesis(),
+            b'[' => self.parse_open_square_bracket(),
+            b']' => self.parse_close_square_bracket(),
             b',' => self.parse_comma(),
+            b'*' => match self.last_token_is_value() {
+                true => self.parse_operator(),
+                false => self.parse_program_counter()
+            },
+            b'+' | b'-' | b'/' | b'&' | b'|' | b'^' | b'~' | b'<' | b'>' | b'!' => self.parse_operator(),
             b'\r' | b'\n' => self.parse_newline(),
             b' ' | b'\t' => self.parse_whitespace(),
             n => {
@@ -241,35 +337,34 @@ impl<'a> Parser<'a> {
 
     fn parse_absolute_decimal(&mut self) -> Result<Token, ParseError> {
         
-        let mut decimal_number: u16 = 0;
+        let mut decimal_number: u32 = 0;
         
         while let Ok(n) = self.peek() {
             let number = match n {
                 n @ b'0'..=b'9' => n - b'0',
-                b' ' | b'\r' | b'\t' | b'\n' | b',' | b')' => break,
+                b' ' | b'\r' | b'\t' | b'\n' | b',' | b')' | b']' => break,
+                n if is_operator(n) => break,
                 _ => return Err(ParseError::InvalidNumberFormat),
             };
 
-            decimal_number = (decimal_number * 10) + number as u16;
+            decimal_number = (decimal_number * 10) + number as u32;
+            if decimal_number > 0xff_ffff {
+                return Err(ParseError::InvalidNumberFormat);
+            }
             let _ = self.eat();
         }
 
-        let size = match decimal_number > 0xff_u16 {
-            true => 2,
-            false => 1
-        };
-
-        match size {
-            1 => Ok(Token::Byte(decimal_number as u8)),
-            2 => Ok(Token::Word(decimal_number)),
-            _ => Err(ParseError::InvalidNumberFormat)
+        match decimal_number {
+            0..=0xff => Ok(Token::Byte(decimal_number as u8)),
+            0x100..=0xffff => Ok(Token::Word(decimal_number as u16)),
+            _ => Ok(Token::Long(decimal_number))
         }
     }
 
     fn parse_hex(&mut self) -> Result<Token, ParseError> {
         self.eat_expected(b'$', ParseError::InvalidNumberFormat)?;
     
-        let mut hex_number: u16 = 0;
+        let mut hex_number: u32 = 0;
         let mut count: u8 = 0;
         
         while let Ok(n) = self.peek() {
@@ -277,22 +372,20 @@ impl<'a> Parser<'a> {
                 b'0'..=b'9' => n - b'0',
                 b'A'..=b'F' => (n - b'A') + 10,
                 b'a'..=b'f' => (n - b'a') + 10,
-                b' ' | b'\r' | b'\t' | b'\n' | b',' | b')' => break,
+                b' ' | b'\r' | b'\t' | b'\n' | b',' | b')' | b']' => break,
+                n if is_operator(n) => break,
                 _ => return Err(ParseError::InvalidNumberFormat),
             };
 
-            hex_number = hex_number << 4 | number as u16;
+            hex_number = hex_number << 4 | number as u32;
             count += 1;
             let _ = self.eat();
         }
         
-        if count != 2 && count != 4 {
-            return Err(ParseError::InvalidNumberFormat);
-        }
-
-        match count / 2 {
-            1 => Ok(Token::Byte(hex_number as u8)),
-            2 => Ok(Token::Word(hex_number)),
+        match count {
+            2 => Ok(Token::Byte(hex_number as u8)),
+            4 => Ok(Token::Word(hex_number as u16)),
+            6 => Ok(Token::Long(hex_number)),
             _ => Err(ParseError::InvalidNumberFormat)
         }
     }
@@ -307,7 +400,8 @@ impl<'a> Parser<'a> {
             let number: u16 = match n {
                 b'0' => 0,
                 b'1' => 1,
-                b' ' | b'\r' | b'\t' | b'\n' | b',' | b')' => break,
+                b' ' | b'\r' | b'\t' | b'\n' | b',' | b')' | b']' => break,
+                n if is_operator(n) => break,
                 _ => return Err(ParseError::InvalidNumberFormat),
             };
 
@@ -338,6 +432,16 @@ impl<'a> Parser<'a> {
         Ok(Token::CloseParenthesis)
     }
 
+    fn parse_open_square_bracket(&mut self) -> Result<Token, ParseError> {
+        self.eat_expected(b'[', ParseError::InvalidNumberFormat)?;
+        Ok(Token::OpenSquareBracket)
+    }
+
+    fn parse_close_square_bracket(&mut self) -> Result<Token, ParseError> {
+        self.eat_expected(b']', ParseError::InvalidNumberFormat)?;
+        Ok(Token::CloseSquareBracket)
+    }
+
     fn parse_sharp(&mut self) -> Result<Token, ParseError> {
         self.eat_expected(b'#', ParseError::InvalidNumberFormat)?;
         Ok(Token::Sharp)
@@ -357,7 +461,8 @@ impl<'a> Parser<'a> {
                         b'a'..=b'z' => valid = true,
                         b'A'..=b'Z' => valid = true,
                         b'_' => (),
-                        b' ' | b',' | b')' | b'=' | b'\t' => break,
+                        b' ' | b',' | b')' | b']' | b'=' | b'\t' => break,
+                        n if is_operator(n) => break,
                         b'\n' | b'\r' => break,
                         b':' => {
                             branch = true;
@@ -381,14 +486,12 @@ impl<'a> Parser<'a> {
             return Ok(Token::Branch(str::from_utf8(&self.data[start..self.index - 1])?.to_string()));
         }
 
-        if self.index - start == 3 {
-            let search_insts: [u8; 3] = [upper_case_byte(self.data[start]), upper_case_byte(self.data[start + 1]), upper_case_byte(self.data[start + 2])];
-            if let Some(position) = INSTS.iter().position(|item| *item == &search_insts) {
-                return Ok(Token::Instr(position));
-            }
+        let keyword = str::from_utf8(&self.data[start..self.index])?;
+        if let Some(position) = INSTS.iter().position(|item| item.eq_ignore_ascii_case(keyword)) {
+            return Ok(Token::Instr(position));
         }
 
-        Ok(Token::Keyword(str::from_utf8(&self.data[start..self.index])?.to_string()))
+        Ok(Token::Keyword(keyword.to_string()))
     }
 
     fn parse_local_branch(&mut self) -> Result<Token, ParseError> {
@@ -405,8 +508,9 @@ impl<'a> Parser<'a> {
                         b'a'..=b'z' => valid = true,
                         b'A'..=b'Z' => valid = true,
                         b'_' => (),
-                        b':' | b'\t' => break,
+                        b':' | b' ' | b'\t' | b',' | b')' | b']' => break,
                         b'\n' | b'\r' => break,
+                        n if is_operator(n) => break,
                         _ => return Err(ParseError::InvalidKeyword),
                     };
                     self.eat()?;
@@ -437,10 +541,8 @@ impl<'a> Parser<'a> {
                 Ok(byte) => {
                     match byte {
                         b'"' => break,
-                        b'\\' => {
-                            if self.peek2()? == b'"' { // It is inline \"
-                                self.eat()?;
-                            }
+                        b'\\' if self.peek2()? == b'"' => { // It is inline \"
+                            self.eat()?;
                         },
                         _ => ()
                     };
@@ -518,6 +620,80 @@ impl<'a> Parser<'a> {
         Ok(Token::Assign)
     }
 
+    fn parse_operator(&mut self) -> Result<Token, ParseError> {
+        let operator = match self.eat()? {
+            b'+' => Operator::Plus,
+            b'-' => Operator::Minus,
+            b'*' => Operator::Multiply,
+            b'/' => Operator::Divide,
+            b'%' => Operator::Modulo,
+            b'&' => match self.peek() {
+                Ok(b'&') => {
+                    self.eat()?;
+                    Operator::LogicalAnd
+                },
+                _ => Operator::And
+            },
+            b'|' => match self.peek() {
+                Ok(b'|') => {
+                    self.eat()?;
+                    Operator::LogicalOr
+                },
+                _ => Operator::Or
+            },
+            b'^' => Operator::Xor,
+            b'~' => Operator::Not,
+            b'=' => {
+                self.eat_expected(b'=', ParseError::UnexpectedSymbol)?;
+                Operator::Equal
+            },
+            b'!' => match self.peek() {
+                Ok(b'=') => {
+                    self.eat()?;
+                    Operator::NotEqual
+                },
+                _ => Operator::LogicalNot
+            },
+            b'<' => match self.peek() {
+                Ok(b'<') => {
+                    self.eat()?;
+                    Operator::ShiftLeft
+                },
+                Ok(b'=') => {
+                    self.eat()?;
+                    Operator::LessEqual
+                },
+                _ => Operator::LessThan
+            },
+            b'>' => match self.peek() {
+                Ok(b'>') => {
+                    self.eat()?;
+                    Operator::ShiftRight
+                },
+                Ok(b'=') => {
+                    self.eat()?;
+                    Operator::GreaterEqual
+                },
+                _ => Operator::GreaterThan
+            },
+            _ => return Err(ParseError::UnexpectedSymbol)
+        };
+        Ok(Token::Operator(operator))
+    }
+
+    fn parse_program_counter(&mut self) -> Result<Token, ParseError> {
+        self.eat()?;
+        Ok(Token::ProgramCounter)
+    }
+
+    /* After a value '%' is modulo and '*' is multiply, everywhere else they are binary number prefix and program counter */
+    fn last_token_is_value(&self) -> bool {
+        let tokens = self.context.tokens.borrow();
+        let last_token = tokens.iter().rev().find(|item| !matches!(item.token, Token::Space(_)));
+
+        matches!(last_token.map(|item| &item.token), Some(Token::Byte(_)) | Some(Token::Word(_)) | Some(Token::Long(_)) | Some(Token::Keyword(_)) | Some(Token::LocalKeyword(_)) | Some(Token::ProgramCounter) | Some(Token::CloseParenthesis))
+    }
+
     fn parse_comma(&mut self) -> Result<Token, ParseError> {
         self.eat_expected(b',', ParseError::UnexpectedSymbol)?;
         Ok(Token::Comma)
@@ -562,14 +738,19 @@ impl<'a> Parser<'a> {
                 Token::Branch(_) => "BRANCH",
                 Token::Byte(_) => "BYTE",
                 Token::Word(_) => "WORD",
+                Token::Long(_) => "LONG",
                 Token::OpenParenthesis => "(",
                 Token::CloseParenthesis => ")",
+                Token::OpenSquareBracket => "[",
+                Token::CloseSquareBracket => "]",
                 Token::Sharp => "#",
+                Token::Operator(_) => "OPERATOR",
                 Token::NewLine(_) => "NEWLINE",
                 Token::Space(_) => "SPACE",
                 Token::End => "END",
                 Token::String(_) => "STRING",
                 Token::Assign => "ASSIGN",
+                Token::ProgramCounter => "PC",
                 Token::Comma => "COMMA",
                 Token::LocalBranch(_) => "LOCAL BR",
                 Token::LocalKeyword(_) => "LOCAL KEY"
@@ -586,3 +767,7 @@ impl<'a> Parser<'a> {
         println!();
     }
 }
+
+fn is_operator(byte: u8) -> bool {
+    matches!(byte, b'+' | b'-' | b'*' | b'/' | b'%' | b'&' | b'|' | b'^' | b'~' | b'<' | b'>' | b'=' | b'!')
+}
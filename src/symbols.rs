@@ -0,0 +1,148 @@
+use std::{fmt::Write, path::{Path, PathBuf}};
+
+use crate::{code_gen::CodeGenerator, context::Context, output::INES_PRG_BANK_SIZE};
+
+#[derive(Debug, PartialEq, Copy, Clone, clap::ValueEnum)]
+pub enum SymbolFormat {
+    /// VICE monitor labels (.lbl)
+    Vice,
+
+    /// FCEUX name lists, one file per bank (.nl)
+    Fceux,
+
+    /// Mesen labels (.mlb)
+    Mesen,
+
+    /// Labels and consts as JSON
+    Json
+}
+
+/* Label with run address and the location in the generated codes */
+#[derive(Debug, PartialEq, Clone)]
+pub struct Label {
+    pub name: String,
+    pub address: usize,
+    pub offset: Option<usize>
+}
+
+/* Local branches are stored as 'scope@name' and macro local branches as 'name#expansion' */
+fn label_name(name: &str) -> String {
+    name.replace('#', "_")
+}
+
+/* Program counter consts are stored as internal branches starting with '*', they are not exported */
+pub fn collect_labels(generator: &CodeGenerator, context: &Context) -> Vec<Label> {
+    let rom_size = generator.chr_start.unwrap_or(context.target.len()).min(context.target.len());
+
+    let mut labels: Vec<Label> = generator.branches.iter()
+        .chain(generator.local_branches.iter())
+        .filter(|(name, _)| !name.starts_with('*'))
+        .map(|(name, address)| Label {
+            name: label_name(name),
+            address: *address,
+            offset: generator.label_offsets.get(name).copied().filter(|offset| *offset < rom_size)
+        })
+        .collect();
+
+    labels.sort_by(|first, second| first.address.cmp(&second.address).then_with(|| first.name.cmp(&second.name)));
+    labels
+}
+
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            character if (character as u32) < 0x20 => { let _ = write!(output, "\\u{:04x}", character as u32); },
+            character => output.push(character)
+        }
+    }
+    output.push('"');
+    output
+}
+
+/* VICE labels can not have '@', local branches are written as 'scope_name' */
+fn generate_vice(labels: &[Label]) -> String {
+    let mut output = String::new();
+    for label in labels.iter() {
+        let _ = writeln!(output, "al C:{:04X} .{}", label.address, label.name.replace('@', "_"));
+    }
+    output
+}
+
+/* RAM labels go to '<rom>.ram.nl' and ROM labels go to '<rom>.<bank>.nl' of their 16KB PRG bank */
+fn generate_fceux(path: &Path, labels: &[Label]) -> Vec<(PathBuf, String)> {
+    let mut files: Vec<(String, String)> = Vec::new();
+
+    for label in labels.iter() {
+        let extension = match (label.address, label.offset) {
+            (address, _) if address < 0x8000 => "ram.nl".to_owned(),
+            (_, Some(offset)) => format!("{:X}.nl", offset / INES_PRG_BANK_SIZE),
+            _ => continue
+        };
+
+        let index = match files.iter().position(|(name, _)| *name == extension) {
+            Some(index) => index,
+            None => {
+                files.push((extension, String::new()));
+                files.len() - 1
+            }
+        };
+
+        let _ = writeln!(files[index].1, "${:04X}#{}#", label.address, label.name);
+    }
+
+    files.into_iter().map(|(extension, content)| {
+        let mut file_name = path.as_os_str().to_owned();
+        file_name.push(".");
+        file_name.push(extension);
+        (PathBuf::from(file_name), content)
+    }).collect()
+}
+
+/* PRG ROM labels use the location in PRG data, the others use the CPU address */
+fn generate_mesen(labels: &[Label]) -> String {
+    let mut output = String::new();
+    for label in labels.iter() {
+        let _ = match (label.address, label.offset) {
+            (address, _) if address < 0x2000 => writeln!(output, "R:{:04X}:{}", address & 0x07ff, label.name),
+            (address, _) if address < 0x6000 => writeln!(output, "G:{:04X}:{}", address, label.name),
+            (address, _) if address < 0x8000 => writeln!(output, "S:{:04X}:{}", address - 0x6000, label.name),
+            (_, Some(offset)) => writeln!(output, "P:{:04X}:{}", offset, label.name),
+            _ => Ok(())
+        };
+    }
+    output
+}
+
+fn generate_json(labels: &[Label], constants: &[(String, i64)]) -> String {
+    let mut output = String::from("{\n  \"labels\": {");
+    for (index, label) in labels.iter().enumerate() {
+        let _ = write!(output, "{}\n    {}: {}", if index == 0 { "" } else { "," }, json_string(&label.name), label.address);
+    }
+
+    output.push_str(if labels.is_empty() { "},\n  \"constants\": {" } else { "\n  },\n  \"constants\": {" });
+    for (index, (name, value)) in constants.iter().enumerate() {
+        let _ = write!(output, "{}\n    {}: {}", if index == 0 { "" } else { "," }, json_string(name), value);
+    }
+
+    output.push_str(if constants.is_empty() { "}\n}\n" } else { "\n  }\n}\n" });
+    output
+}
+
+/* Returns the files to write, FCEUX uses the path as ROM file name and generates many files */
+pub fn generate_symbols(format: SymbolFormat, path: &Path, generator: &CodeGenerator, context: &Context) -> Vec<(PathBuf, String)> {
+    let labels = collect_labels(generator, context);
+
+    match format {
+        SymbolFormat::Vice => vec![(path.to_path_buf(), generate_vice(&labels))],
+        SymbolFormat::Fceux => generate_fceux(path, &labels),
+        SymbolFormat::Mesen => vec![(path.to_path_buf(), generate_mesen(&labels))],
+        SymbolFormat::Json => {
+            let mut constants = generator.constants(context);
+            constants.sort();
+            vec![(path.to_path_buf(), generate_json(&labels, &constants))]
+        }
+    }
+}
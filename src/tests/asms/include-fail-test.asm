@@ -0,0 +1,4 @@
+.org $0600
+    NOP
+.include "sub-fail-file.asm"
+    RTS
@@ -0,0 +1,3 @@
+; Illegal addressing mode for 6502
+    LDA #$01
+    STA ($10)
@@ -0,0 +1,94 @@
+use rstest::*;
+
+use crate::{disasm::{disassemble, DisasmOptions}, opcode::CpuType, tests::assemble};
+
+fn compile(data: &'_ [u8], cpu: CpuType) -> Vec<u8> {
+    let (_, context) = assemble("main.asm", data, cpu);
+    context.target
+}
+
+#[rstest]
+#[case(&[0xa2, 0x00, 0xbd, 0x10, 0xc0, 0xe8, 0xd0, 0xfa, 0x20, 0x0c, 0xc0, 0x60, 0x0a, 0x60], DisasmOptions { start: 0xc000, ..Default::default() }, ".org $C000
+    LDX #$00
+L_C002:
+    LDA $C010,X
+    INX
+    BNE L_C002
+    JSR L_C00C
+    RTS
+L_C00C:
+    ASL
+    RTS
+")]
+#[case(&[0xea, 0x01, 0x02, 0x4c, 0x00, 0x06], DisasmOptions { start: 0x0600, data: vec![0x0601..=0x0602], ..Default::default() }, ".org $0600
+L_0600:
+    NOP
+    .byte $01, $02
+    JMP L_0600
+")]
+#[case(&[0xea, 0xea, 0xea, 0xea], DisasmOptions { code: vec![0x0000..=0x0001], ..Default::default() }, ".org $00
+    NOP
+    NOP
+    .byte $EA, $EA
+")]
+#[case(&[0xad, 0x12, 0x00, 0x20, 0x01, 0x00, 0xd0, 0xfe, 0xff], DisasmOptions::default(), ".org $00
+    LDA a:$0012
+    JSR a:$0001
+L_0006:
+    BNE L_0006
+    .byte $FF
+")]
+#[case(&[0x5a, 0x0f, 0x10, 0xfd, 0x80, 0xfa], DisasmOptions { start: 0x0200, cpu: CpuType::Rockwell65C02, ..Default::default() }, ".cpu \"R65C02\"
+.org $0200
+L_0200:
+    PHY
+L_0201:
+    BBR0 $10, L_0201
+    BRA L_0200
+")]
+#[case(&[0xc2, 0x30, 0xa9, 0x34, 0x12, 0xa2, 0x00, 0x00, 0xe2, 0x20, 0xa9, 0x12, 0x54, 0x7f, 0x7e, 0x22, 0x56, 0x34, 0x12], DisasmOptions { start: 0x8000, cpu: CpuType::Wdc65816, ..Default::default() }, ".cpu \"65816\"
+.org $8000
+    REP #$30
+    LDA #$1234
+    LDX #$0000
+    SEP #$20
+    LDA #$12
+    MVN $7E, $7F
+    JSL $123456
+")]
+#[case(&[0xd0, 0xff, 0x00, 0x80, 0xfe], DisasmOptions::default(), ".org $00
+    BNE *+1
+    BRK
+    .byte $80, $FE
+")]
+#[case(&[0xa7, 0x33, 0x03], DisasmOptions { start: 0x0000, cpu: CpuType::Mos6502X, ..Default::default() }, ".cpu \"6502X\"
+.org $00
+    LAX $33
+    .byte $03
+")]
+fn disasm_test(#[case] data: &[u8], #[case] options: DisasmOptions, #[case] expected: &str) {
+    let source = disassemble(data, &options);
+    assert_eq!(source, expected);
+    assert_eq!(compile(source.as_bytes(), options.cpu), data);
+}
+
+/* Every opcode is disassembled and assembled again */
+#[rstest]
+#[case(CpuType::Mos6502, 0xc000)]
+#[case(CpuType::Mos6502X, 0xc000)]
+#[case(CpuType::Cmos65C02, 0xc000)]
+#[case(CpuType::Rockwell65C02, 0x0010)]
+#[case(CpuType::Wdc65C02, 0xff00)]
+#[case(CpuType::Wdc65816, 0x8000)]
+#[case(CpuType::Wdc65816, 0x7e8000)]
+fn disasm_roundtrip_test(#[case] cpu: CpuType, #[case] start: u32) {
+    let options = DisasmOptions { start, cpu, ..Default::default() };
+
+    for opcode in 0..=0xff {
+        for operand in [[0x12, 0x34, 0x56], [0x00, 0x00, 0x00], [0xfe, 0xff, 0x80]] {
+            let data = [opcode, operand[0], operand[1], operand[2]];
+            let source = disassemble(&data, &options);
+            assert_eq!(compile(source.as_bytes(), cpu), data, "{}", source);
+        }
+    }
+}
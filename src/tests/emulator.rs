@@ -0,0 +1,65 @@
+use rstest::*;
+
+use crate::{emulator::{Emulator, EmulatorError, StopReason, DEFAULT_MAX_CYCLES}, opcode::CpuType, tests::assemble};
+
+fn load(data: &'_ [u8]) -> (Emulator, u16) {
+    let (generator, context) = assemble("main.asm", data, CpuType::default());
+
+    let mut emulator = Emulator::new();
+    emulator.load(generator.start_point, &context.target).unwrap();
+    (emulator, generator.start_point as u16)
+}
+
+#[rstest]
+#[case(b".org $0600\nLDA #$01\nLDX #$02\nLDY #$03\nRTS", StopReason::Return, "PC=$0000 A=$01 X=$02 Y=$03 S=$FD P=$24 (nv-bdIzc) Cycles=18")]
+#[case(b".org $0600\nLDA #$80\nBRK", StopReason::Break, "PC=$0602 A=$80 X=$00 Y=$00 S=$FB P=$A4 (Nv-bdIzc) Cycles=8")]
+#[case(b".org $0600\nLDX #$03\nloop: DEX\nBNE loop\nend: JMP end", StopReason::Loop, "PC=$0605 A=$00 X=$00 Y=$00 S=$FB P=$26 (nv-bdIZc) Cycles=25")]
+#[case(b".org $0600\nJSR sub\nINY\nRTS\nsub: INX\nRTS", StopReason::Return, "PC=$0000 A=$00 X=$01 Y=$01 S=$FD P=$24 (nv-bdIzc) Cycles=28")]
+#[case(b".org $0600\nSED\nCLC\nLDA #$19\nADC #$28\nRTS", StopReason::Return, "PC=$0000 A=$47 X=$00 Y=$00 S=$FD P=$2C (nv-bDIzc) Cycles=20")]
+#[case(b".org $0600\nSED\nSEC\nLDA #$10\nSBC #$01\nRTS", StopReason::Return, "PC=$0000 A=$09 X=$00 Y=$00 S=$FD P=$2D (nv-bDIzC) Cycles=20")]
+#[case(b".org $0600\nCLC\nLDA #$7F\nADC #$01\nRTS", StopReason::Return, "PC=$0000 A=$80 X=$00 Y=$00 S=$FD P=$E4 (NV-bdIzc) Cycles=18")]
+#[case(b".org $0600\nLDX #$01\nLDA $06FF,X\nRTS", StopReason::Return, "PC=$0000 A=$00 X=$01 Y=$00 S=$FD P=$26 (nv-bdIZc) Cycles=19")]
+#[case(b".org $0600\nLDA #$12\nPHA\nLDA #$00\nPLA\nRTS", StopReason::Return, "PC=$0000 A=$12 X=$00 Y=$00 S=$FD P=$24 (nv-bdIzc) Cycles=23")]
+fn run_test(#[case] data: &'_ [u8], #[case] reason: StopReason, #[case] registers: &str) {
+    let (mut emulator, start) = load(data);
+    assert_eq!(emulator.call(start, DEFAULT_MAX_CYCLES), Ok(reason));
+    assert_eq!(emulator.registers_text(), registers);
+}
+
+#[rstest]
+#[case(b".org $0600\nLDA #$00\nSTA $10\nLDA #$07\nSTA $11\nLDY #$02\nLDA #$AA\nSTA ($10),Y\nRTS", 0x0702, 0xaa)]
+#[case(b".org $0600\nLDA #$0F\nSTA $20\nASL $20\nINC $20\nRTS", 0x0020, 0x1f)]
+#[case(b".org $0600\nLDA #<target\nSTA $02FF\nLDA #>target\nSTA $0200\nJMP ($02FF)\ntarget: LDA #$55\nSTA $30\nRTS", 0x0030, 0x55)]
+fn memory_test(#[case] data: &'_ [u8], #[case] address: usize, #[case] expected: u8) {
+    let (mut emulator, start) = load(data);
+    emulator.call(start, DEFAULT_MAX_CYCLES).unwrap();
+    assert_eq!(emulator.memory[address], expected);
+}
+
+#[rstest]
+#[case(b".org $0600\nloop: LDA $F000\nBEQ end\nCLC\nADC #$01\nSTA $F001\nJMP loop\nend: RTS", b"HAL", b"IBM")]
+#[case(b".org $0600\nLDA $F000\nSTA $F001\nLDA $F000\nSTA $F001\nRTS", b"", b"\0\0")]
+fn port_test(#[case] data: &'_ [u8], #[case] input: &[u8], #[case] output: &[u8]) {
+    let (mut emulator, start) = load(data);
+    emulator.stdin_port = Some(0xf000);
+    emulator.stdout_port = Some(0xf001);
+    emulator.input.extend(input);
+
+    assert_eq!(emulator.call(start, DEFAULT_MAX_CYCLES), Ok(StopReason::Return));
+    assert_eq!(emulator.output, output);
+}
+
+#[rstest]
+#[case(b".org $0600\nNOP\n.byte $02", 1000, EmulatorError::IllegalOpcode(0x02, 0x0601))]
+#[case(b".org $0600\nloop: INX\nJMP loop", 100, EmulatorError::CycleLimit(100, 0x0600))]
+fn run_failure(#[case] data: &'_ [u8], #[case] max_cycles: u64, #[case] error: EmulatorError) {
+    let (mut emulator, start) = load(data);
+    assert_eq!(emulator.call(start, max_cycles), Err(error));
+}
+
+#[rstest]
+#[case(0xfff0, 0x20)]
+fn load_failure(#[case] address: u32, #[case] size: usize) {
+    let mut emulator = Emulator::new();
+    assert_eq!(emulator.load(address, &vec![0; size]), Err(EmulatorError::ProgramTooBig(address as usize + size)));
+}
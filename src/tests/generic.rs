@@ -3,10 +3,13 @@ use std::{fs::File, io::Read, path::PathBuf};
 use rstest::*;
 
 use crate::{
-    ast::{AstGenerator, InstrInfo, InstrValue, InstrInfoRegister},
+    ast::{Ast, AstGenerator, BinaryOperator, Expr, InstrInfo, InstrValue, InstrInfoRegister, OperandSize},
     code_gen::{CodeGenerator, CodeGeneratorError},
     context::Context,
+    directive::DirectiveValue,
+    opcode::CpuType,
     parser::Parser,
+    tool::{line_text, parse_define},
 };
 
 #[rstest]
@@ -81,20 +84,25 @@ fn compile_test(#[case] data: &'_ [u8]) {
 }
 
 #[rstest]
-#[case(br#"#$08"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: true, in_parenthesis: false, register: InstrInfoRegister::None })]
-#[case(br#"#$0008"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: true, in_parenthesis: false, register: InstrInfoRegister::None })]
-#[case(br#"$08"#,  InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: false, register: InstrInfoRegister::None })]
-#[case(br#"$0800"#,  InstrInfo { value: InstrValue::Word(0x0800), is_immediate: false, in_parenthesis: false, register: InstrInfoRegister::None })]
-#[case(br#"($0800)"#, InstrInfo { value: InstrValue::Word(0x0800), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::None })]
-#[case(br#"($0008, X)"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::X })]
-#[case(br#"($0008) , Y"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::Y })]
-#[case(br#"( $08, X ) "#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::X })]
-#[case(br#"( $08 ) , Y "#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::Y })]
-#[case(br#"( test ) , Y "#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::Y })]
-#[case(br#"(test),Y"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::Y })]
-#[case(br#"#test"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: true, in_parenthesis: false, register: InstrInfoRegister::None })]
-#[case(br#"(test)"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: true, register: InstrInfoRegister::None })]
-#[case(br#"test"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: false, register: InstrInfoRegister::None })]
+#[case(br#"#$08"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: true, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"#$0008"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: true, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"$08"#,  InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"$0800"#,  InstrInfo { value: InstrValue::Word(0x0800), is_immediate: false, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"($0800)"#, InstrInfo { value: InstrValue::Word(0x0800), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"($0008, X)"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::X, size: OperandSize::Auto })]
+#[case(br#"($0008) , Y"#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::Y, size: OperandSize::Auto })]
+#[case(br#"( $08, X ) "#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::X, size: OperandSize::Auto })]
+#[case(br#"( $08 ) , Y "#, InstrInfo { value: InstrValue::Byte(0x08), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::Y, size: OperandSize::Auto })]
+#[case(br#"( test ) , Y "#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::Y, size: OperandSize::Auto })]
+#[case(br#"(test),Y"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::Y, size: OperandSize::Auto })]
+#[case(br#"#test"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: true, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"(test)"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"test"#, InstrInfo { value: InstrValue::Reference("test".to_string()), is_immediate: false, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"test+1,X"#, InstrInfo { value: InstrValue::Expression(Expr::Binary(BinaryOperator::Add, Box::new(Expr::Reference("test".to_string())), Box::new(Expr::Byte(1)))), is_immediate: false, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::X, size: OperandSize::Auto })]
+#[case(br#"#(1 + 2) * 3"#, InstrInfo { value: InstrValue::Expression(Expr::Binary(BinaryOperator::Multiply, Box::new(Expr::Binary(BinaryOperator::Add, Box::new(Expr::Byte(1)), Box::new(Expr::Byte(2)))), Box::new(Expr::Byte(3)))), is_immediate: true, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::None, size: OperandSize::Auto })]
+#[case(br#"a:$10,X"#, InstrInfo { value: InstrValue::Byte(0x10), is_immediate: false, in_parenthesis: false, in_bracket: false, register: InstrInfoRegister::X, size: OperandSize::Word })]
+#[case(br#"(Z:ptr),Y"#, InstrInfo { value: InstrValue::Reference("ptr".to_string()), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::Y, size: OperandSize::Byte })]
+#[case(br#"(ptr+2),Y"#, InstrInfo { value: InstrValue::Expression(Expr::Binary(BinaryOperator::Add, Box::new(Expr::Reference("ptr".to_string())), Box::new(Expr::Byte(2)))), is_immediate: false, in_parenthesis: true, in_bracket: false, register: InstrInfoRegister::Y, size: OperandSize::Auto })]
 fn number_parsing_test(#[case] data: &'_ [u8], #[case] expected: InstrInfo) {
     let context = Context::default();
     let path = PathBuf::from("main.asm");
@@ -227,7 +235,6 @@ LDx IOREST"#, &[0xad, 0x4a, 0xff, 0xae, 0x3f, 0xff])]
 #[case(br#".word $ccff"#, &[0xff, 0xcc])]
 #[case(br#".word $ff"#, &[0xff, 0x00])]
 #[case(br#"AND #$dd"#, &[0x29, 0xdd])]
-#[case(br#"AND #$ffdd"#, &[0x29, 0xdd])]
 #[case(br#"AND $dd"#, &[0x25, 0xdd])]
 #[case(br#"AND $ffdd"#, &[0x2d, 0xdd, 0xff])]
 #[case(br#"AND ($ff, x)"#, &[0x21, 0xff])]
@@ -258,6 +265,250 @@ CPX #var1"#, &[0xe0, 0x10])]
 #[case(br#".dsb 5 , $10"#, &[0x10, 0x10, 0x10, 0x10, 0x10])]
 #[case(br#".dsw 5"#, &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])]
 #[case(br#".dsw 5 , $1122"#, &[0x22, 0x11, 0x22, 0x11, 0x22, 0x11, 0x22, 0x11, 0x22, 0x11])]
+#[case(br#"SIZE = 10
+.byte SIZE*2, SIZE"#, &[0x14, 0x0a])]
+#[case(br#"MAX = $10
+CMP #(MAX-1)"#, &[0xc9, 0x0f])]
+#[case(br#".byte 1+2*3, (1+2)*3, 10 % 3, 10 / 3, -1"#, &[0x07, 0x09, 0x01, 0x03, 0xff])]
+#[case(br#".byte $F0 | $0F, $FF & $0F, $0F ^ $FF, ~$0F"#, &[0xff, 0x0f, 0xf0, 0xf0])]
+#[case(br#".word 1 << 8, $8000 >> 15, $F0 + $20"#, &[0x00, 0x01, 0x01, 0x00, 0x10, 0x01])]
+#[case(br#"BASE = $20
+OFFSET = BASE + 2
+LDA OFFSET
+LDA #-1
+STA BASE*2,X"#, &[0xa5, 0x22, 0xa9, 0xff, 0x95, 0x40])]
+#[case(br#"LDA table+1,X
+table:
+.byte $01, $02"#, &[0xb5, 0x03, 0x01, 0x02])]
+#[case(br#"lax = 5
+sre = $10
+rep = 2
+bra:
+LDA #lax
+STA sre
+LDX #rep
+JMP bra
+JSR pea
+pea:
+wai: stp: RTS"#, &[0xa9, 0x05, 0x85, 0x10, 0xa2, 0x02, 0x4c, 0x00, 0x00, 0x20, 0x0c, 0x00, 0x60])]
+#[case(br#"PTR = $0010
+LDA PTR
+LDX $0020,Y
+STA $0030"#, &[0xa5, 0x10, 0xb6, 0x20, 0x85, 0x30])]
+#[case(br#"LDA a:$10
+STA a:$0010,X
+JMP (a:$10)"#, &[0xad, 0x10, 0x00, 0x9d, 0x10, 0x00, 0x6c, 0x10, 0x00])]
+#[case(br#".org $0080
+LDA z:value
+value:"#, &[0xa5, 0x82])]
+#[case(br#".org $00FC
+LDA data
+NOP
+data:"#, &[0xad, 0x00, 0x01, 0xea])]
+#[case(br#"loop:
+NOP
+BNE loop+1
+JMP (loop + $10)"#, &[0xea, 0xd0, 0xfe, 0x6c, 0x10, 0x00])]
+#[case(br#".org $C000
+LDA #<message
+STA $00
+LDA #>message
+STA $01
+RTS
+message:
+.byte $48"#, &[0xa9, 0x09, 0x85, 0x00, 0xa9, 0xc0, 0x85, 0x01, 0x60, 0x48])]
+#[case(br#"message:
+.byte $48
+LDA #<message
+LDX #>(message + $0100)"#, &[0x48, 0xa9, 0x00, 0xa2, 0x01])]
+#[case(br#"FAR = $12 << 16 | $3456
+PTR = $1234
+.byte ^FAR, <FAR, >FAR
+LDA <PTR
+LDA >PTR,X"#, &[0x12, 0x56, 0x34, 0xa5, 0x34, 0xb5, 0x12])]
+#[case(br#".org $8000
+table:
+.word nmi, reset, irq
+.word reset-1
+.byte <reset, >reset
+nmi:
+RTI
+reset:
+RTS
+irq:
+RTI"#, &[0x0a, 0x80, 0x0b, 0x80, 0x0c, 0x80, 0x0a, 0x80, 0x0b, 0x80, 0x40, 0x60, 0x40])]
+#[case(br#"start:
+NOP
+.word start, start+1
+.byte <start, end-start
+end:"#, &[0xea, 0x00, 0x00, 0x01, 0x00, 0x00, 0x07])]
+#[case(br#"LDA FOO
+FOO = $10"#, &[0xa5, 0x10])]
+#[case(br#".org BASE
+JMP start
+start:
+BASE = $C000"#, &[0x4c, 0x03, 0xc0])]
+#[case(br#".byte len
+start:
+NOP
+NOP
+end:
+len = end - start"#, &[0x02, 0xea, 0xea])]
+#[case(br#"first:
+BNE @done
+NOP
+@done:
+second:
+BEQ @done
+@done:
+RTS"#, &[0xd0, 0x01, 0xea, 0xf0, 0x00, 0x60])]
+#[case(br#".org $8000
+.byte $01
+.org $8004
+.byte $02"#, &[0x01, 0x00, 0x00, 0x00, 0x02])]
+#[case(br#".org $8000
+JMP copy
+.base $0200
+copy:
+JMP copy
+.rend
+after:
+.word after"#, &[0x4c, 0x00, 0x02, 0x4c, 0x00, 0x02, 0x06, 0x80])]
+#[case(br#".org $8000
+.rorg $0200
+loop:
+BNE loop
+.org $8004
+.word loop"#, &[0xd0, 0xfe, 0x00, 0x00, 0x00, 0x02])]
+#[case(br#"LDA later
+later:"#, &[0xa5, 0x02])]
+#[case(br#".org $10
+.pad $14
+.byte $01"#, &[0x00, 0x00, 0x00, 0x00, 0x01])]
+#[case(br#".org $018000
+.pad $018002
+NOP"#, &[0x00, 0x00, 0xea])]
+#[case(br#".org $10
+.pad $10
+.byte $01"#, &[0x01])]
+#[case(br#".org $C000
+JMP *
+JMP $"#, &[0x4c, 0x00, 0xc0, 0x4c, 0x03, 0xc0])]
+#[case(br#"*=$0600
+start:
+NOP
+NOP
+len = * - start
+.byte len, <*, >*
+BNE *"#, &[0xea, 0xea, 0x02, 0x02, 0x06, 0xd0, 0xfe])]
+#[case(br#".org $10
+.byte * * 2"#, &[0x20])]
+#[case(br#".macro PPU_ADDR addr
+    LDA #>addr
+    STA $2006
+    LDA #<addr
+    STA $2006
+.endm
+PPU_ADDR $2000"#, &[0xa9, 0x20, 0x8d, 0x06, 0x20, 0xa9, 0x00, 0x8d, 0x06, 0x20])]
+#[case(br#".macro inc_by value=1, target
+    LDA target
+    CLC
+    ADC #value
+    STA target
+.endm
+inc_by target=$10
+inc_by 2, $11 ; comment"#, &[0xa5, 0x10, 0x18, 0x69, 0x01, 0x85, 0x10, 0xa5, 0x11, 0x18, 0x69, 0x02, 0x85, 0x11])]
+#[case(br#".macro wait count
+    LDX #count
+@loop:
+    DEX
+    BNE @loop
+.endm
+start:
+    wait 2
+    wait (1 + 2) * 1"#, &[0xa2, 0x02, 0xca, 0xd0, 0xfd, 0xa2, 0x03, 0xca, 0xd0, 0xfd])]
+#[case(br#".macro store value, address
+    LDA #value
+    STA address
+.endm
+.MACRO clear address
+    store 0, address
+.ENDMACRO
+clear $20"#, &[0xa9, 0x00, 0x85, 0x20])]
+#[case(br#"PAL = 1
+.if PAL == 1
+    LDA #$01
+.elseif PAL == 2
+    LDA #$02
+.else
+    LDA #$03
+.endif"#, &[0xa9, 0x01])]
+#[case(br#"PAL = 3
+.if PAL == 1
+    LDA #$01
+.elif PAL == 2
+    LDA #$02
+.else
+    LDA #$03
+.endif"#, &[0xa9, 0x03])]
+#[case(br#"DEBUG = 1
+start:
+.ifdef DEBUG
+    .ifndef RELEASE
+        NOP
+    .else
+        BRK
+    .endif
+.endif
+.ifdef RELEASE
+    .include "missing.asm"
+    .if UNKNOWN
+    .endif
+.endif
+.ifdef start
+    NOP
+.endif"#, &[0xea, 0xea])]
+#[case(br#".byte 1 < 2, 2 <= 1, 3 == 3, 3 != 3, !0, 1 && 0, 1 || 0, 1 + 1 == 2"#, &[0x01, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x01])]
+#[case(br#".macro fill count
+.if count > 0
+    NOP
+    fill count - 1
+.endif
+.endm
+fill 3"#, &[0xea, 0xea, 0xea])]
+#[case(br#".rept 3
+    NOP
+.endr"#, &[0xea, 0xea, 0xea])]
+#[case(br#".rept 4, i
+    .byte i * i
+.endr"#, &[0x00, 0x01, 0x04, 0x09])]
+#[case(br#"start:
+.rept 2
+    LDX #2
+@loop:
+    DEX
+    BNE @loop
+.endr"#, &[0xa2, 0x02, 0xca, 0xd0, 0xfd, 0xa2, 0x02, 0xca, 0xd0, 0xfd])]
+#[case(br#".rept 2, i
+    .rept 2, j
+        .byte i * 2 + j
+    .endr
+.endr"#, &[0x00, 0x01, 0x02, 0x03])]
+#[case(br#"COUNT = 2
+.repeat COUNT ; comment
+    NOP
+.endrep
+.rept 0
+    BRK
+.endr"#, &[0xea, 0xea])]
+#[case(br#"ASL
+ASL A
+LSR a ; comment
+ROL $10
+ROR $1234,X"#, &[0x0a, 0x0a, 0x4a, 0x26, 0x10, 0x7e, 0x34, 0x12])]
+#[case(br#"LDA $10,Y
+STA $20,Y
+JMP ($10)"#, &[0xb9, 0x10, 0x00, 0x99, 0x20, 0x00, 0x6c, 0x10, 0x00])]
 fn check_codes(#[case] data: &'_ [u8], #[case] codes: &'_ [u8]) {
     let context = Context::default();
     let path = PathBuf::from("main.asm");
@@ -332,6 +583,67 @@ fn parser_fail(#[case] data: &'_ [u8]) {
 VAR = 1
 VAR = 1
 "#)]
+#[case(br#".macro test
+NOP"#)]
+#[case(br#".endm"#)]
+#[case(br#".macro test value
+.endm
+test"#)]
+#[case(br#".macro test value
+.endm
+test 1, 2"#)]
+#[case(br#".macro test
+.endm
+.macro test
+.endm"#)]
+#[case(br#".macro test
+    test
+.endm
+test"#)]
+#[case(br#".macro test
+.macro inner
+.endm
+.endm"#)]
+#[case(br#".if 1
+NOP"#)]
+#[case(br#".endif"#)]
+#[case(br#".else"#)]
+#[case(br#".elseif 1"#)]
+#[case(br#".if 1
+.else
+.else
+.endif"#)]
+#[case(br#".if FOO
+.endif
+FOO = 1"#)]
+#[case(br#".if UNKNOWN
+.endif"#)]
+#[case(br#".if 1 2
+.endif"#)]
+#[case(br#".ifdef 1
+.endif"#)]
+#[case(br#".rept 2
+NOP"#)]
+#[case(br#".endr"#)]
+#[case(br#".rept UNKNOWN
+.endr"#)]
+#[case(br#".rept 2, 1
+.endr"#)]
+#[case(br#".rept -1
+.endr"#)]
+#[case(br#".rept $FFFF+2, i
+.endr"#)]
+#[case(br#".rept 65536
+.rept 65536
+NOP
+.endr
+.endr"#)]
+#[case(br#"LDA b:$10"#)]
+#[case(br#".macro load x
+LDA $10,x
+.endm"#)]
+#[case(br#".macro load value, Y=1
+.endm"#)]
 fn ast_generator_fail(#[case] data: &'_ [u8]) {
     let context = Context::default();
         let path = PathBuf::from("main.asm");
@@ -373,6 +685,61 @@ fn compile_failure(#[case] data: &'_ [u8]) {
     };
 }
 
+#[rstest]
+#[case(br#".byte 1/0"#)]
+#[case(br#".byte $ff+1"#)]
+#[case(br#".word $FFFF+2"#)]
+#[case(br#".word -$8001"#)]
+#[case(br#"A = B
+B = A
+LDA A"#)]
+#[case(br#"LDA unknown+1"#)]
+#[case(br#".word missing"#)]
+#[case(br#".org $C000
+.byte label
+label:"#)]
+#[case(br#"label:
+NOP
+label:"#)]
+#[case(br#"FOO = $10
+FOO:"#)]
+#[case(br#".org $8000
+NOP
+.org $7000"#)]
+#[case(br#".org $8000
+NOP
+.pad $8000"#)]
+#[case(br#".pad $FFFFFF+1"#)]
+#[case(br#"BNE @missing
+@other:"#)]
+#[case(br#"LDA z:$1234"#)]
+#[case(br#"JMP z:$10"#)]
+#[case(br#"LDA (a:$10),Y"#)]
+#[case(br#"JSR z:label
+label:"#)]
+#[case(br#"AND #$ffdd"#)]
+#[case(br#"LDA #255+2"#)]
+#[case(br#".org $C000
+start:
+LDA #start"#)]
+fn expression_failure(#[case] data: &'_ [u8]) {
+    let context = Context::default();
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap();
+
+    let context = parser.context;
+
+    let ast_generator = AstGenerator::new();
+    let context = ast_generator.generate(context).unwrap();
+
+    let mut generator = CodeGenerator::new();
+    assert!(generator.generate(context).is_err());
+}
+
 #[rstest]
 #[case("src/tests/asms/tables.asm", "src/tests/bins/tables.bin")]
 #[case("src/tests/asms/import-test.asm", "src/tests/bins/import-test.bin")]
@@ -458,4 +825,377 @@ fn local_branch_test(#[case] data: &'_ [u8]) {
     let mut generator = CodeGenerator::new();
     let context = generator.generate(context).unwrap();
     generator.dump(&context);
-}
\ No newline at end of file
+}
+#[rstest]
+#[case("DEBUG", Some(("DEBUG", DirectiveValue::Byte(1))))]
+#[case("REGION=2", Some(("REGION", DirectiveValue::Byte(2))))]
+#[case("BASE=$C000", Some(("BASE", DirectiveValue::Word(0xc000))))]
+#[case("_MASK = %1010", Some(("_MASK", DirectiveValue::Byte(0b1010))))]
+#[case("1NAME", None)]
+#[case("NAME=", None)]
+#[case("NAME=$10000", None)]
+#[case("NAME=abc", None)]
+fn define_parse(#[case] text: &str, #[case] expected: Option<(&str, DirectiveValue)>) {
+    let result = parse_define(text).ok();
+    assert_eq!(result, expected.map(|(name, value)| (name.to_string(), value)));
+}
+
+/* Errors print only the line of the error */
+#[rstest]
+#[case(b"LDA undefined1\nNOP\nRTS", 0, "LDA undefined1")]
+#[case(b"LDA undefined1\r\nNOP\r\nRTS", 1, "NOP")]
+#[case(b"LDA undefined1\nNOP\nRTS", 2, "RTS")]
+#[case(b"NOP", 0, "NOP")]
+#[case(b"NOP\n", 1, "")]
+fn line_text_test(#[case] data: &'_ [u8], #[case] line: usize, #[case] expected: &str) {
+    assert_eq!(line_text(data, line), expected);
+}
+
+#[rstest]
+#[case(&["PAL"], &[0x32])]
+#[case(&["PAL=0"], &[0x3c])]
+#[case(&["PAL", "SPEED=3"], &[0x32, 0x03])]
+fn define_test(#[case] defines: &[&str], #[case] codes: &'_ [u8]) {
+    let data = br#".if PAL
+    .byte 50
+.else
+    .byte 60
+.endif
+.ifdef SPEED
+    .byte SPEED
+.endif"#;
+
+    let context = Context::default();
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    for define in defines.iter() {
+        let (name, value) = parse_define(define).unwrap();
+        context.references.borrow_mut().insert(name, vec![value]);
+    }
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap();
+
+    let context = parser.context;
+
+    let ast_generator = AstGenerator::new();
+    let context = ast_generator.generate(context).unwrap();
+
+    let mut generator = CodeGenerator::new();
+    let context = generator.generate(context).unwrap();
+    assert_eq!(context.target, codes);
+}
+
+#[rstest]
+#[case(CpuType::Cmos65C02, br#"BRA next
+PHX
+PHY
+PLX
+PLY
+next:
+STZ $10
+STZ $10,X
+STZ $1234
+STZ $1234,X"#, &[0x80, 0x04, 0xda, 0x5a, 0xfa, 0x7a, 0x64, 0x10, 0x74, 0x10, 0x9c, 0x34, 0x12, 0x9e, 0x34, 0x12])]
+#[case(CpuType::Cmos65C02, br#"TRB $10
+TSB $1234
+LDA ($10)
+STA ($20)
+JMP ($1234,X)
+JMP ($10)
+BIT #$80
+INC
+DEC A"#, &[0x14, 0x10, 0x0c, 0x34, 0x12, 0xb2, 0x10, 0x92, 0x20, 0x7c, 0x34, 0x12, 0x6c, 0x10, 0x00, 0x89, 0x80, 0x1a, 0x3a])]
+#[case(CpuType::Rockwell65C02, br#"loop:
+RMB0 $10
+SMB7 $10
+BBR3 $10, loop
+BBS1 $20,next
+next:"#, &[0x07, 0x10, 0xf7, 0x10, 0x3f, 0x10, 0xf9, 0x9f, 0x20, 0x00])]
+#[case(CpuType::Mos6502, br#".cpu "w65c02"
+WAI
+STP
+.cpu "6502"
+NOP"#, &[0xcb, 0xdb, 0xea])]
+#[case(CpuType::Mos6502X, br#"LAX $10
+LAX ($10),Y
+SAX $20,Y
+DCP $1234,X
+ISC ($10,X)
+SLO $10
+RLA $1234,Y
+SRE $10,X
+RRA $10"#, &[0xa7, 0x10, 0xb3, 0x10, 0x97, 0x20, 0xdf, 0x34, 0x12, 0xe3, 0x10, 0x07, 0x10, 0x3b, 0x34, 0x12, 0x57, 0x10, 0x67, 0x10])]
+#[case(CpuType::Mos6502X, br#"ANC #$0f
+ALR #$fe
+ARR #$7f
+SBX #$01
+NOP
+NOP #$00
+NOP $10
+JAM"#, &[0x0b, 0x0f, 0x4b, 0xfe, 0x6b, 0x7f, 0xcb, 0x01, 0xea, 0x80, 0x00, 0x04, 0x10, 0x02])]
+#[case(CpuType::Mos6502, br#".cpu "6502x"
+ASO $10
+LSE $10
+DCM $10
+ISB $10
+ASR #$01
+AXS #$02"#, &[0x07, 0x10, 0x47, 0x10, 0xc7, 0x10, 0xe7, 0x10, 0x4b, 0x01, 0xcb, 0x02])]
+#[case(CpuType::Wdc65816, br#"LDA $123456
+LDA $123456,X
+LDA [$10]
+LDA [$10],Y
+LDA $03,S
+LDA ($05,S),Y
+JML [$1234]
+JSL $C08000
+JMP $7E1234"#, &[0xaf, 0x56, 0x34, 0x12, 0xbf, 0x56, 0x34, 0x12, 0xa7, 0x10, 0xb7, 0x10, 0xa3, 0x03, 0xb3, 0x05, 0xdc, 0x34, 0x12, 0x22, 0x00, 0x80, 0xc0, 0x5c, 0x34, 0x12, 0x7e])]
+#[case(CpuType::Wdc65816, br#"LDA #$12
+.a16
+LDA #$1234
+LDX #$12
+.i16
+LDY #$0012
+.a8
+.i8
+CPX #$34"#, &[0xa9, 0x12, 0xa9, 0x34, 0x12, 0xa2, 0x12, 0xa0, 0x12, 0x00, 0xe0, 0x34])]
+#[case(CpuType::Wdc65816, br#"REP #$30
+LDA #$1234
+LDX #$5678
+SEP #$20
+LDA #$12
+LDY #$0012
+SEP #$10
+LDX #$12"#, &[0xc2, 0x30, 0xa9, 0x34, 0x12, 0xa2, 0x78, 0x56, 0xe2, 0x20, 0xa9, 0x12, 0xa0, 0x12, 0x00, 0xe2, 0x10, 0xa2, 0x12])]
+#[case(CpuType::Wdc65816, br#"start:
+BRL start
+PER start
+MVN $7E, $7F
+MVP #$01, #$02
+PEA $1234
+PEI ($10)
+XBA
+XCE
+RTL"#, &[0x82, 0xfd, 0xff, 0x62, 0xfa, 0xff, 0x54, 0x7f, 0x7e, 0x44, 0x02, 0x01, 0xf4, 0x34, 0x12, 0xd4, 0x10, 0xeb, 0xfb, 0x6b])]
+#[case(CpuType::Wdc65816, br#".org $C08000
+start:
+JMP start
+JSR start
+LDA start
+.byte ^start, >start, <start"#, &[0x5c, 0x00, 0x80, 0xc0, 0x20, 0x00, 0x80, 0xaf, 0x00, 0x80, 0xc0, 0xc0, 0x80, 0x00])]
+#[case(CpuType::Mos6502, br#".cpu "65816"
+.a16
+LDA #$1234
+.cpu "6502"
+LDA #$12"#, &[0xa9, 0x34, 0x12, 0xa9, 0x12])]
+#[case(CpuType::Mos6502, br#"USE_X = 0
+.if USE_X
+.cpu "6502x"
+.endif
+lax = 5
+LDA #lax"#, &[0xa9, 0x05])]
+#[case(CpuType::Mos6502, br#".macro load
+LAX $10
+.endm
+.cpu "6502x"
+load
+.cpu "6502"
+lax = 5
+LDA #lax"#, &[0xa7, 0x10, 0xa9, 0x05])]
+fn cpu_test(#[case] cpu: CpuType, #[case] data: &'_ [u8], #[case] codes: &'_ [u8]) {
+    let context = Context { cpu, ..Default::default() };
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap();
+
+    let context = parser.context;
+
+    let ast_generator = AstGenerator::new();
+    let context = ast_generator.generate(context).unwrap();
+
+    let mut generator = CodeGenerator::new();
+    generator.cpu = cpu;
+    let context = generator.generate(context).unwrap();
+    assert_eq!(context.target, codes);
+}
+
+#[rstest]
+#[case(CpuType::Mos6502, b"PHX")]
+#[case(CpuType::Mos6502, b"LDA ($10)")]
+#[case(CpuType::Mos6502, b"INC")]
+#[case(CpuType::Mos6502, b"JMP ($1234,X)")]
+#[case(CpuType::Cmos65C02, b"RMB0 $10")]
+#[case(CpuType::Rockwell65C02, b"WAI")]
+#[case(CpuType::Rockwell65C02, b"BBR0 $1234, $10")]
+#[case(CpuType::Cmos65C02, br#".cpu "6502"
+BRA $10"#)]
+#[case(CpuType::Mos6502, br#".cpu "z80""#)]
+#[case(CpuType::Mos6502, b"LAX $10")]
+#[case(CpuType::Mos6502, b"NOP #$00")]
+#[case(CpuType::Cmos65C02, b"SLO $10")]
+#[case(CpuType::Mos6502X, b"PHX")]
+#[case(CpuType::Mos6502X, b"SAX $10,X")]
+#[case(CpuType::Mos6502, b"LDA $123456")]
+#[case(CpuType::Cmos65C02, b"LDA [$10]")]
+#[case(CpuType::Wdc65C02, b"XBA")]
+#[case(CpuType::Wdc65816, b"BBR0 $10, $10")]
+#[case(CpuType::Wdc65816, b"LDA [$1234],Y")]
+#[case(CpuType::Wdc65816, b"MVN $1234, $10")]
+#[case(CpuType::Wdc65816, b".a16\nLDA #$123456")]
+fn cpu_failure(#[case] cpu: CpuType, #[case] data: &'_ [u8]) {
+    let context = Context { cpu, ..Default::default() };
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap();
+
+    let context = parser.context;
+
+    /* Mnemonics of the other CPUs are names, using them as instructions fails in the ast generator */
+    let ast_generator = AstGenerator::new();
+    let context = match ast_generator.generate(context) {
+        Ok(context) => context,
+        Err(_) => return
+    };
+
+    let mut generator = CodeGenerator::new();
+    generator.cpu = cpu;
+    assert!(generator.generate(context).is_err());
+}
+
+/* Every stage runs after the errors of the previous stages, expected values are the parser, ast generator and code generator errors */
+#[rstest]
+#[case(b"LDA 12a\nNOP\nLDX 3x\nLDY 4y", 20, (3, 0, 0))]
+#[case(b"LDA 12a\nNOP\nLDX 3x\nLDY 4y", 2, (2, 0, 0))]
+#[case(b"LDA 12a\nNOP\nLDX 3x\nLDY 4y", 0, (1, 0, 0))]
+#[case(b"LDA #1 2\nNOP\n)\nLDA ]\n.endr", 20, (0, 4, 0))]
+#[case(b".if 1\nLDA #1 2\nNOP", 20, (0, 2, 0))]
+#[case(b".org $0600\nSTA ($10)\nJMP missing\nLDX other\nROR ($10),Y\nBRK", 20, (0, 0, 4))]
+#[case(b".org $0600\nJMP missing\nJMP missing\nlabel:\nlabel:", 20, (0, 0, 3))]
+#[case(b".org $0600\nJMP missing\nJMP missing\nJMP missing", 1, (0, 0, 3))]
+#[case(b"LDA #1 +\nJMP missing\nJMP missing2", 20, (0, 1, 2))]
+#[case(b"LDA 12a\nLDA #1 +\nJMP missing\nJMP missing2", 20, (1, 1, 2))]
+#[case(b"LDA 12a\nLDA #1 +\nJMP missing\nJMP missing2", 2, (1, 1, 0))]
+#[case(b"LDA #1 +\nJMP missing", 0, (0, 1, 0))]
+#[case(b".if later\n.byte 1\n.endif\nlater = 1", 20, (0, 1, 0))]
+#[case(b".if * > 3\n.byte 1\n.elseif\n.byte 2\n.else\n.byte 3\n.endif", 20, (0, 1, 0))]
+#[case(b".if 0\n.elseif later\n.byte 1\n.endif\nlater = 1", 20, (0, 1, 0))]
+#[case(b".rept -1\nNOP\n.endr\nNOP", 20, (0, 1, 0))]
+#[case(b".rept 2, 1\n.rept 2\nNOP\n.endr\n.endr\nNOP", 20, (0, 1, 0))]
+#[case(b".rept\nNOP\n.endr", 20, (0, 1, 0))]
+fn error_count_test(#[case] data: &'_ [u8], #[case] max_errors: usize, #[case] expected: (usize, usize, usize)) {
+    let context = Context { max_errors, ..Default::default() };
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    let mut parser = Parser::new(0, data, context);
+    let parsed = parser.parse();
+    assert_eq!(parser.total_errors, expected.0);
+
+    let mut context = parser.context;
+    let ast_generator = AstGenerator::new();
+    let generated = match context.error_limit_reached() {
+        true => Ok(()),
+        false => ast_generator.generate_in_place(&context)
+    };
+    assert_eq!(ast_generator.total_errors.get(), expected.1);
+
+    let mut generator = CodeGenerator::new();
+    let compiled = match context.error_limit_reached() {
+        true => Ok(()),
+        false => generator.generate_in_place(&mut context)
+    };
+    assert_eq!(generator.total_errors, expected.2);
+
+    assert!(parsed.is_err() || generated.is_err() || compiled.is_err());
+    assert_eq!(context.total_errors.get(), expected.0 + expected.1 + expected.2);
+}
+
+/* Errors in included files are reported with the file of the ast and the include location */
+#[rstest]
+#[case("src/tests/asms/include-fail-test.asm", 2, 2)]
+fn include_fail_test(#[case] code_filename: &str, #[case] include_line: usize, #[case] error_line: usize) {
+    let mut code = Vec::new();
+    let mut file = File::open(code_filename).unwrap();
+    file.read_to_end(&mut code).unwrap();
+
+    let context = Context::default();
+    let path = PathBuf::from(code_filename);
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = code.clone();
+
+    let mut parser = Parser::new(0, &code, context);
+    parser.parse().unwrap();
+
+    let ast_generator = AstGenerator::new();
+    let context = ast_generator.generate(parser.context).unwrap();
+
+    assert_eq!(context.code_files.borrow()[1].included_from, Some((0, include_line)));
+    assert!(context.asts.borrow().iter().any(|ast| ast.file_id == 1 && ast.line == error_line && matches!(ast.ast, Ast::Instr(..))));
+
+    let mut generator = CodeGenerator::new();
+    assert!(matches!(generator.generate(context), Err(CodeGeneratorError::ModeNotAvailable("STA", _))));
+    assert_eq!(generator.total_errors, 1);
+}
+
+/* Expected values are the branch bytes, the bytes after the filler and the label address */
+#[rstest]
+#[case(CpuType::Mos6502, true, b".org $0600\nBNE far\n.dsb 200, $ea\nfar:\nRTS", &[0xf0, 0x03, 0x4c, 0xcd, 0x06], &[0x60], 0x06cd)]
+#[case(CpuType::Mos6502, true, b".org $0600\nback:\n.dsb 130, $ea\nBEQ back\nnext:\nRTS", &[0xd0, 0x03, 0x4c, 0x00, 0x06], &[0x60], 0x0687)]
+#[case(CpuType::Mos6502, false, b".org $0600\n.longbranch on\nBCC far\n.dsb 200, $ea\nfar:\nRTS", &[0xb0, 0x03, 0x4c, 0xcd, 0x06], &[0x60], 0x06cd)]
+#[case(CpuType::Cmos65C02, false, b".org $0600\n.longbranch 1\nBRA far\n.dsb 200, $ea\nfar:\nRTS", &[0x4c, 0xcb, 0x06], &[0x60], 0x06cb)]
+#[case(CpuType::Mos6502, true, b".org $0600\nBNE far\n.dsb 100, $ea\nfar:\nRTS", &[0xd0, 0x64], &[0x60], 0x0666)]
+#[case(CpuType::Wdc65816, true, b".org $0600\nBRL far\n.dsb 200, $ea\nfar:\nRTS", &[0x82, 0xc8, 0x00], &[0x60], 0x06cb)]
+fn branch_relax_test(#[case] cpu: CpuType, #[case] relax_branches: bool, #[case] data: &'_ [u8], #[case] branch: &'_ [u8], #[case] end: &'_ [u8], #[case] label: usize) {
+    let context = Context { cpu, ..Default::default() };
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap();
+
+    let ast_generator = AstGenerator::new();
+    let context = ast_generator.generate(parser.context).unwrap();
+
+    let mut generator = CodeGenerator::new();
+    generator.cpu = cpu;
+    generator.relax_branches = relax_branches;
+    let context = generator.generate(context).unwrap();
+
+    let label_name = ["far", "next"].into_iter().find(|name| generator.branches.contains_key(*name)).unwrap();
+    assert!(context.target.starts_with(branch) || context.target.ends_with(&[branch, end].concat()), "{:02X?}", context.target);
+    assert!(context.target.ends_with(end));
+    assert_eq!(generator.branches.get(label_name), Some(&label));
+}
+
+#[rstest]
+#[case(CpuType::Mos6502, b".org $0600\nBNE far\n.dsb 200, $ea\nfar:", 200)]
+#[case(CpuType::Mos6502, b".org $0600\nback:\n.dsb 130, $ea\nBEQ back", -132)]
+#[case(CpuType::Mos6502, b".longbranch on\n.longbranch off\nBPL far\n.dsb 128, $ea\nfar:", 128)]
+#[case(CpuType::Rockwell65C02, b".longbranch on\nBBR0 $10, far\n.dsb 128, $ea\nfar:", 128)]
+fn branch_failure(#[case] cpu: CpuType, #[case] data: &'_ [u8], #[case] distance: i64) {
+    let context = Context { cpu, ..Default::default() };
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap();
+
+    let ast_generator = AstGenerator::new();
+    let context = ast_generator.generate(parser.context).unwrap();
+
+    let mut generator = CodeGenerator::new();
+    generator.cpu = cpu;
+    let result = generator.generate(context);
+    assert!(matches!(result, Err(CodeGeneratorError::BranchOutOfRange(value, -128, 127)) if value == distance), "{:?}", result.err());
+}
@@ -0,0 +1,91 @@
+use std::{fs::File, io::Read, path::Path};
+
+use rstest::*;
+
+use crate::{listing::generate_listing, opcode::CpuType, tests::assemble};
+
+fn compile(path: &str, data: &'_ [u8]) -> String {
+    let (generator, context) = assemble(path, data, CpuType::default());
+
+    /* File headers have full paths, only file names are checked */
+    generate_listing(&generator, &context).lines().map(|line| match line.strip_prefix("; ") {
+        Some(path) => format!("; {}", Path::new(path).file_name().unwrap().to_string_lossy()),
+        None => line.to_owned()
+    }).collect::<Vec<_>>().join("\n")
+}
+
+#[rstest]
+#[case(br#"; test
+.org $C000
+reset:
+    LDA #$01
+    JMP reset"#, r#"; main.asm
+    1                      ; test
+    2  C000                .org $C000
+    3  C000                reset:
+    4  C000   A9 01            LDA #$01
+    5  C002   4C 00 C0         JMP reset"#)]
+#[case(br#".macro store value, addr
+    LDA #value
+    STA addr
+.endm
+.org $0600
+    store 1, $10
+    RTS"#, r#"; main.asm
+    1                      .macro store value, addr
+    2                          LDA #value
+    3                          STA addr
+    4                      .endm
+    5  0600                .org $0600
+    6                          store 1, $10
+    2+ 0600   A9 01            LDA #value
+    3+ 0602   85 10            STA addr
+    7  0604   60               RTS"#)]
+#[case(br#".rept 2
+@loop:
+    DEX
+.endr
+.byte 1, 2, 3, 4, 5, 6"#, r#"; main.asm
+    1                      .rept 2
+    2  0000                @loop:
+    3  0000   CA               DEX
+    2  0001                @loop:
+    3  0001   CA               DEX
+    4                      .endr
+    5  0002   01 02 03 04  .byte 1, 2, 3, 4, 5, 6
+              05 06"#)]
+#[case(br#".org $8000
+.dsb 40, $ff
+.base $0200
+ram:
+.rend"#, r#"; main.asm
+    1  8000                .org $8000
+    2  8000   FF FF FF FF  .dsb 40, $ff
+              FF FF FF FF
+              FF FF FF FF
+              FF FF FF FF
+              FF FF FF FF
+              FF FF FF FF
+              FF FF FF FF
+              ...
+    3  8028                .base $0200
+    4  0200                ram:
+    5  0200                .rend"#)]
+fn listing_test(#[case] data: &'_ [u8], #[case] expected: &str) {
+    assert_eq!(compile("main.asm", data), expected);
+}
+
+#[rstest]
+#[case("src/tests/asms/import-test.asm")]
+fn listing_include_test(#[case] code_filename: &str) {
+    let mut code = Vec::new();
+    File::open(code_filename).unwrap().read_to_end(&mut code).unwrap();
+
+    let listing = compile(code_filename, &code);
+    let lines: Vec<&str> = listing.lines().collect();
+
+    assert_eq!(&lines[..4], &["; import-test.asm", "    1  0000                .include \"sub-file.asm\"", "; sub-file.asm", "; import-test.asm"]);
+    assert!(lines.contains(&"; sub-2-file.asm"));
+    assert!(lines.contains(&"    1  0040   00 11 22 33  .byte $00, $11, $22, $33, $44, $55, $66, $77, $88, $99, $AA, $BB, $CC, $DD, $EE, $FF"));
+    assert_eq!(lines.last(), Some(&"   14  0060                .warning \"test warning\""));
+}
@@ -1,2 +1,30 @@
+use std::path::PathBuf;
+
+use crate::{ast::AstGenerator, code_gen::CodeGenerator, context::Context, opcode::CpuType, parser::Parser};
+
+mod disasm;
+mod emulator;
 mod generic;
-mod parser;
\ No newline at end of file
+mod listing;
+mod output;
+mod parser;
+mod symbols;
+mod unit_test;
+
+/* Code is assembled as the main file, every stage has to succeed */
+fn assemble(path: &str, data: &'_ [u8], cpu: CpuType) -> (CodeGenerator, Context) {
+    let context = Context { cpu, ..Default::default() };
+    context.add_file(0, PathBuf::from(path));
+    context.code_files.borrow_mut()[0].data = data.to_vec();
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap_or_else(|error| panic!("{:?} {}", error, String::from_utf8_lossy(data)));
+
+    let ast_generator = AstGenerator::new();
+    let context = ast_generator.generate(parser.context).unwrap();
+
+    let mut generator = CodeGenerator::new();
+    generator.cpu = cpu;
+    let context = generator.generate(context).unwrap();
+    (generator, context)
+}
@@ -0,0 +1,136 @@
+use rstest::*;
+
+use crate::{opcode::CpuType, output::{generate_output, OutputError, OutputFormat}, tests::assemble};
+
+fn compile(data: &'_ [u8], format: OutputFormat) -> Result<Vec<u8>, OutputError> {
+    compile_with_stub(data, format, None)
+}
+
+fn compile_with_stub(data: &'_ [u8], format: OutputFormat, basic_start: Option<u16>) -> Result<Vec<u8>, OutputError> {
+    let (generator, context) = assemble("main.asm", data, CpuType::default());
+    generate_output(format, &generator, &context, basic_start)
+}
+
+#[rstest]
+#[case(br#".inesprg 1
+.ineschr 1
+.inesmap 1
+.inesmir 1
+.org $C000
+NOP
+.chr
+.byte $11"#, &[0x4e, 0x45, 0x53, 0x1a, 0x01, 0x01, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], 1, 1)]
+#[case(br#".inesprg 2
+.inesmap 258
+.inesmir %00000011
+.nes2sub 1
+.nes2prgram 8192
+.nes2bram 8192
+.nes2chrram 8192
+.nes2tv 1
+NOP"#, &[0x4e, 0x45, 0x53, 0x1a, 0x02, 0x00, 0x23, 0x08, 0x11, 0x00, 0x77, 0x07, 0x01, 0x00, 0x00, 0x00], 2, 0)]
+#[case(br#".inesprg 1
+.fillvalue $ff
+NOP"#, &[0x4e, 0x45, 0x53, 0x1a, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], 1, 0)]
+fn ines_test(#[case] data: &'_ [u8], #[case] header: &[u8], #[case] prg_banks: usize, #[case] chr_banks: usize) {
+    let output = compile(data, OutputFormat::Ines).unwrap();
+    assert_eq!(&output[..16], header);
+    assert_eq!(output.len(), 16 + prg_banks * 16384 + chr_banks * 8192);
+    assert_eq!(output[16], 0xea);
+
+    if chr_banks > 0 {
+        assert_eq!(output[16 + prg_banks * 16384], 0x11);
+    }
+}
+
+#[rstest]
+fn ines_padding_test() {
+    let output = compile(br#".inesprg 1
+.fillvalue $ff
+NOP"#, OutputFormat::Ines).unwrap();
+    assert!(output[17..].iter().all(|byte| *byte == 0xff));
+
+    let output = compile(br#".inesprg 1
+NOP"#, OutputFormat::Binary).unwrap();
+    assert_eq!(output, &[0xea]);
+}
+
+#[rstest]
+#[case(b"NOP")]
+#[case(br#".inesprg 1
+.dsb $4001"#)]
+#[case(br#".inesprg 1
+.chr
+.byte $11"#)]
+#[case(br#".inesprg 1
+.ineschr 1
+.chr
+.dsw $1001"#)]
+#[case(br#".inesprg 1
+.inesmir 16"#)]
+#[case(br#".inesprg 1
+.nes2prgram 1000"#)]
+#[case(br#".inesprg 1
+.nes2tv 4"#)]
+fn ines_failure(#[case] data: &'_ [u8]) {
+    assert!(compile(data, OutputFormat::Ines).is_err());
+}
+
+#[rstest]
+#[case(br#".org $C000
+NOP"#, None, &[0x00, 0xc0, 0xea])]
+#[case(br#"*=$0810
+start:
+    JMP start"#, Some(0x0801), &[0x01, 0x08, 0x0b, 0x08, 0x0a, 0x00, 0x9e, 0x32, 0x30, 0x36, 0x34, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x4c, 0x10, 0x08])]
+#[case(br#".org $100D
+RTS"#, Some(0x1001), &[0x01, 0x10, 0x0b, 0x10, 0x0a, 0x00, 0x9e, 0x34, 0x31, 0x30, 0x39, 0x00, 0x00, 0x00, 0x60])]
+fn prg_test(#[case] data: &'_ [u8], #[case] basic_start: Option<u16>, #[case] expected: &[u8]) {
+    let output = compile_with_stub(data, OutputFormat::Prg, basic_start).unwrap();
+    assert_eq!(output, expected);
+}
+
+#[rstest]
+#[case(br#".org $0808
+NOP"#, OutputFormat::Prg)]
+#[case(b"NOP", OutputFormat::Prg)]
+#[case(br#".org $0810
+NOP"#, OutputFormat::Binary)]
+fn prg_failure(#[case] data: &'_ [u8], #[case] format: OutputFormat) {
+    assert!(compile_with_stub(data, format, Some(0x0801)).is_err());
+}
+
+#[rstest]
+#[case(b"", ":0400000300000000F9\n:00000001FF\n")]
+#[case(br#".org $C000
+LDA #$01
+RTS"#, ":03C00000A9016033\n:040000030000C00039\n:00000001FF\n")]
+#[case(br#".org $0300
+.byte $00, $01, $02, $03, $04, $05, $06, $07, $08, $09, $0A, $0B, $0C, $0D, $0E, $0F, $10
+.org $0400
+.byte $FF"#, ":10030000000102030405060708090A0B0C0D0E0F75\n:0103100010DC\n:01040000FFFC\n:0400000300000300F6\n:00000001FF\n")]
+#[case(br#".org $FFFF
+.byte $01, $02"#, ":01FFFF000100\n:020000040001F9\n:0100000002FD\n:040000030000FFFFFB\n:00000001FF\n")]
+#[case(br#".cpu "65816"
+.org $7E0000
+.byte $AA"#, ":02000004007E7C\n:01000000AA55\n:04000005007E000079\n:00000001FF\n")]
+fn ihex_test(#[case] data: &'_ [u8], #[case] expected: &str) {
+    let output = compile(data, OutputFormat::Ihex).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+}
+
+#[rstest]
+#[case(b"", "S0030000FC\nS5030000FC\nS9030000FC\n")]
+#[case(br#".org $C000
+LDA #$01
+RTS"#, "S0030000FC\nS106C000A901602F\nS5030001FB\nS903C0003C\n")]
+#[case(br#".org $0300
+.byte $12
+.org $0310
+.byte $34"#, "S0030000FC\nS104030012E6\nS104031034B4\nS5030002FA\nS9030300F9\n")]
+#[case(br#".cpu "65816"
+.org $7E0000
+.byte $AA"#, "S0030000FC\nS2057E0000AAD2\nS5030001FB\nS8047E00007D\n")]
+fn srec_test(#[case] data: &'_ [u8], #[case] expected: &str) {
+    let output = compile(data, OutputFormat::Srec).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+}
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use rstest::*;
 
-use crate::{ast::AstGenerator, context::Context, parser::{Parser, Token}};
+use crate::{ast::AstGenerator, context::Context, parser::{Operator, Parser, Token}};
 
 #[rstest]
 // Hex numbers
@@ -15,7 +15,11 @@ use crate::{ast::AstGenerator, context::Context, parser::{Parser, Token}};
 
 // Decimal numbers
 #[case(b"160", 0xa0)]
-fn number_check(#[case] data: &'_ [u8], #[case] expected: u16) {
+
+// Long numbers
+#[case(b"$123456", 0x123456)]
+#[case(b"70000", 70000)]
+fn number_check(#[case] data: &'_ [u8], #[case] expected: u32) {
     let context = Context::default();
     let path = PathBuf::from("main.asm");
     context.add_file(0, path);
@@ -25,7 +29,8 @@ fn number_check(#[case] data: &'_ [u8], #[case] expected: u16) {
     assert_eq!(parser.context.tokens.borrow().len(), 2);
     match parser.context.tokens.borrow()[0].token {
         Token::Byte(current) => assert_eq!(current, expected as u8),
-        Token::Word(current) => assert_eq!(current, expected),
+        Token::Word(current) => assert_eq!(current, expected as u16),
+        Token::Long(current) => assert_eq!(current, expected),
         _ => panic!("Unexpected token")
     }
     assert_eq!(parser.context.tokens.borrow()[1].token, Token::End);
@@ -42,6 +47,8 @@ fn number_check(#[case] data: &'_ [u8], #[case] expected: u16) {
 #[case(b"#$1")]
 #[case(b"$a01")]
 #[case(b"$a0111")]
+#[case(b"$a011122")]
+#[case(b"16777216")]
 #[case(b"$a")]
 #[case(b"$ta000")]
 #[case(b"$a000-,X")]
@@ -60,7 +67,7 @@ fn invalid_number_check(#[case] data: &'_ [u8]) {
   
     let mut parser = Parser::new(0, data, context);
 
-    if let Ok(_) = parser.parse() {
+    if parser.parse().is_ok() {
         let ast_generator = AstGenerator::new();
         ast_generator.generate(parser.context).unwrap_err();
     }
@@ -86,3 +93,35 @@ fn check_comment(#[case] data: &'_ [u8]) {
 
     panic!("Comment not parsed")
 }
+
+#[rstest]
+#[case(b"1+2", &[Token::Byte(1), Token::Operator(Operator::Plus), Token::Byte(2)])]
+#[case(b"label-1", &[Token::Keyword("label".to_string()), Token::Operator(Operator::Minus), Token::Byte(1)])]
+#[case(b"$10*%00000010", &[Token::Byte(0x10), Token::Operator(Operator::Multiply), Token::Byte(2)])]
+#[case(b"10 % 3", &[Token::Byte(10), Token::Space(1), Token::Operator(Operator::Modulo), Token::Space(1), Token::Byte(3)])]
+#[case(b"(1<<8)>>2", &[Token::OpenParenthesis, Token::Byte(1), Token::Operator(Operator::ShiftLeft), Token::Byte(8), Token::CloseParenthesis, Token::Operator(Operator::ShiftRight), Token::Byte(2)])]
+#[case(b"~$0F&$FF|1^2/3", &[Token::Operator(Operator::Not), Token::Byte(0x0f), Token::Operator(Operator::And), Token::Byte(0xff), Token::Operator(Operator::Or), Token::Byte(1), Token::Operator(Operator::Xor), Token::Byte(2), Token::Operator(Operator::Divide), Token::Byte(3)])]
+#[case(b"#<label", &[Token::Sharp, Token::Operator(Operator::LessThan), Token::Keyword("label".to_string())])]
+#[case(b">label<<1", &[Token::Operator(Operator::GreaterThan), Token::Keyword("label".to_string()), Token::Operator(Operator::ShiftLeft), Token::Byte(1)])]
+#[case(b"*-start", &[Token::ProgramCounter, Token::Operator(Operator::Minus), Token::Keyword("start".to_string())])]
+#[case(b"*=$C000", &[Token::ProgramCounter, Token::Assign, Token::Word(0xc000)])]
+#[case(b"$ - 2*3", &[Token::ProgramCounter, Token::Space(1), Token::Operator(Operator::Minus), Token::Space(1), Token::Byte(2), Token::Operator(Operator::Multiply), Token::Byte(3)])]
+#[case(b"* * *", &[Token::ProgramCounter, Token::Space(1), Token::Operator(Operator::Multiply), Token::Space(1), Token::ProgramCounter])]
+#[case(b"a==1", &[Token::Keyword("a".to_string()), Token::Operator(Operator::Equal), Token::Byte(1)])]
+#[case(b"a!=!b", &[Token::Keyword("a".to_string()), Token::Operator(Operator::NotEqual), Token::Operator(Operator::LogicalNot), Token::Keyword("b".to_string())])]
+#[case(b"1<=2>=3", &[Token::Byte(1), Token::Operator(Operator::LessEqual), Token::Byte(2), Token::Operator(Operator::GreaterEqual), Token::Byte(3)])]
+#[case(b"a&&b||c", &[Token::Keyword("a".to_string()), Token::Operator(Operator::LogicalAnd), Token::Keyword("b".to_string()), Token::Operator(Operator::LogicalOr), Token::Keyword("c".to_string())])]
+fn operator_check(#[case] data: &'_ [u8], #[case] expected: &[Token]) {
+    let context = Context::default();
+    let path = PathBuf::from("main.asm");
+    context.add_file(0, path);
+
+    let mut parser = Parser::new(0, data, context);
+    parser.parse().unwrap();
+
+    let tokens = parser.context.tokens.borrow();
+    assert_eq!(tokens.len(), expected.len() + 1);
+    for (token, expected) in tokens.iter().zip(expected.iter()) {
+        assert_eq!(&token.token, expected);
+    }
+}
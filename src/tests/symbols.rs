@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use rstest::*;
+
+use crate::{opcode::CpuType, symbols::{generate_symbols, SymbolFormat}, tests::assemble};
+
+fn compile(data: &'_ [u8], format: SymbolFormat) -> Vec<(PathBuf, String)> {
+    let (generator, context) = assemble("main.asm", data, CpuType::default());
+    generate_symbols(format, Path::new("game.nes"), &generator, &context)
+}
+
+const PROGRAM: &[u8] = br#"PPU = $2000
+ptr = $10
+start = *
+.org $C000
+reset:
+    LDA #$00
+@loop:
+    INX
+    BNE @loop
+    JMP ram
+.base $0300
+ram:
+    RTS
+.rend
+.org $FFFC
+.word reset"#;
+
+#[rstest]
+#[case(SymbolFormat::Vice, "al C:0300 .ram\nal C:C000 .reset\nal C:C002 .reset_loop\n")]
+#[case(SymbolFormat::Mesen, "R:0300:ram\nP:0000:reset\nP:0002:reset@loop\n")]
+#[case(SymbolFormat::Json, "{\n  \"labels\": {\n    \"ram\": 768,\n    \"reset\": 49152,\n    \"reset@loop\": 49154\n  },\n  \"constants\": {\n    \"PPU\": 8192,\n    \"ptr\": 16,\n    \"start\": 0\n  }\n}\n")]
+fn symbol_test(#[case] format: SymbolFormat, #[case] expected: &str) {
+    let files = compile(PROGRAM, format);
+    assert_eq!(files, vec![(PathBuf::from("game.nes"), expected.to_owned())]);
+}
+
+#[rstest]
+#[case(PROGRAM, &[("game.nes.ram.nl", "$0300#ram#\n"), ("game.nes.0.nl", "$C000#reset#\n$C002#reset@loop#\n")])]
+#[case(br#".org $8000
+first:
+    NOP
+.org $C000
+second:
+    NOP
+third = $0400"#, &[("game.nes.0.nl", "$8000#first#\n"), ("game.nes.1.nl", "$C000#second#\n")])]
+#[case(b"NOP", &[])]
+fn fceux_test(#[case] data: &'_ [u8], #[case] expected: &[(&str, &str)]) {
+    let files = compile(data, SymbolFormat::Fceux);
+    let expected: Vec<(PathBuf, String)> = expected.iter().map(|(path, content)| (PathBuf::from(path), content.to_string())).collect();
+    assert_eq!(files, expected);
+}
+
+#[rstest]
+#[case(b"", "{\n  \"labels\": {},\n  \"constants\": {}\n}\n")]
+#[case(br#".macro wait
+@loop:
+    DEX
+    BNE @loop
+.endm
+main:
+    wait"#, "{\n  \"labels\": {\n    \"main\": 0,\n    \"main@loop_1\": 0\n  },\n  \"constants\": {}\n}\n")]
+#[case(br#"text = "hello"
+table = $10, $20
+value = (1 + 2) * 3"#, "{\n  \"labels\": {},\n  \"constants\": {\n    \"value\": 9\n  }\n}\n")]
+fn json_test(#[case] data: &'_ [u8], #[case] expected: &str) {
+    let files = compile(data, SymbolFormat::Json);
+    assert_eq!(files[0].1, expected);
+}
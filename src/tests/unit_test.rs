@@ -0,0 +1,74 @@
+use rstest::*;
+
+use crate::{opcode::CpuType, tests::assemble, unit_test::{embedded_spec, file_spec, parse_spec, run_test, TestResult, TestSpecError}};
+
+const MULTIPLY: &[u8] = br#"num1 = $10
+num2 = $11
+result = $12
+
+.org $0600
+multiply:
+    LDA #0
+    LDX #8
+@loop:
+    LSR num2
+    BCC @skip
+    CLC
+    ADC num1
+@skip:
+    ROR A
+    ROR result
+    DEX
+    BNE @loop
+    STA result+1
+    RTS
+stop:
+    BRK
+table:
+    .byte $34, $12"#;
+
+fn run(spec: &str) -> Result<Vec<TestResult>, TestSpecError> {
+    let (generator, context) = assemble("main.asm", MULTIPLY, CpuType::default());
+    let tests = parse_spec(&file_spec("main.spec", spec), &generator, &context)?;
+    Ok(tests.iter().map(|test| run_test(test, &context.target, generator.start_point, 1000)).collect())
+}
+
+#[rstest]
+#[case("test three by four\n    set [num1]=3 [num2]=4\n    call multiply\n    expect [result]=$0C, $00 X=0 Z=1 cycles=182\nend", "three by four", 182, &[])]
+#[case("# comment\ntest \"big\"\n    set [num1] = $FF [num2] = $FF\n    call multiply\n    expect [result] = $01, $FE A=$FE cycles <= 200\nend", "big", 210, &["Cycles: expected at most 200, found 210"])]
+#[case("test wrong\n    set [num1]=2 [num2]=2 C=1\n    call $0600\n    expect [result]=5 A=1 C=1 cycles=100\nend", "wrong", 182, &["[$0012]: expected $05, found $04", "A: expected $01, found $00", "C: expected 1, found 0", "Cycles: expected 100, found 182"])]
+#[case("test pointer\n    set A=<table X=>table\n    expect A=$15 X=$06 [table+1]=$12 [table]=$34 cycles=0\nend", "pointer", 0, &[])]
+#[case("test brk\n    call stop\n    expect A=1\nend", "brk", 6, &["stop stopped with BRK at $0614"])]
+#[case("test twice\n    set [num1]=1 [num2]=1\n    call multiply\n    call multiply\nend", "twice", 178, &[])]
+fn unit_test_test(#[case] spec: &str, #[case] name: &str, #[case] cycles: u64, #[case] failures: &[&str]) {
+    let results = run(spec).unwrap();
+    assert_eq!(results, vec![TestResult { name: name.to_owned(), cycles, failures: failures.iter().map(|failure| failure.to_string()).collect() }]);
+}
+
+#[rstest]
+#[case("set A=1", TestSpecError::Syntax("main.spec:1".to_owned(), "'test' expected, 'set' found".to_owned()))]
+#[case("test first\n    call nothing\nend", TestSpecError::UnknownName("main.spec:2".to_owned(), "nothing".to_owned()))]
+#[case("test first\n    set A=1\n\ntest second\nend", TestSpecError::EndExpected("main.spec:1".to_owned(), "first".to_owned()))]
+#[case("test first\n    expect A=$100\nend", TestSpecError::Syntax("main.spec:2".to_owned(), "'$100' is bigger than a byte".to_owned()))]
+#[case("test first\n    expect C=2\nend", TestSpecError::Syntax("main.spec:2".to_owned(), "C flag could be 0 or 1".to_owned()))]
+#[case("test first\n    expect Q=2\nend", TestSpecError::Syntax("main.spec:2".to_owned(), "'Q' is not a register, flag or memory address".to_owned()))]
+#[case("test first\n    set cycles=2\nend", TestSpecError::Syntax("main.spec:2".to_owned(), "'cycles' is not a register, flag or memory address".to_owned()))]
+#[case("test first\n    expect A<=2\nend", TestSpecError::Syntax("main.spec:2".to_owned(), "'<=' is only supported for cycles, 'A' found".to_owned()))]
+#[case("test first\n    jump multiply\nend", TestSpecError::Syntax("main.spec:2".to_owned(), "'set', 'call', 'expect' or 'end' expected, 'jump' found".to_owned()))]
+#[case("test first", TestSpecError::EndExpected("main.spec:1".to_owned(), "first".to_owned()))]
+fn unit_test_failure(#[case] spec: &str, #[case] error: TestSpecError) {
+    assert_eq!(run(spec), Err(error));
+}
+
+#[rstest]
+#[case(b".org $0600\nincrement:\n    INX\n    RTS\n;! test increment\n   ;!     set X=$FF\n;!     call increment\n;!     expect X=0 Z=1 cycles=14\n;! end", 5)]
+fn embedded_spec_test(#[case] data: &'_ [u8], #[case] lines: usize) {
+    let (generator, context) = assemble("main.asm", data, CpuType::default());
+    let spec = embedded_spec(&context);
+    assert_eq!(spec.len(), lines);
+    assert!(spec[0].0.ends_with("main.asm:5"));
+
+    let tests = parse_spec(&spec, &generator, &context).unwrap();
+    assert_eq!(tests.len(), 1);
+    assert!(run_test(&tests[0], &context.target, generator.start_point, 1000).passed());
+}
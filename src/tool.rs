@@ -1,39 +1,130 @@
 use core::str;
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::RangeInclusive, rc::Rc};
 
 use log::error;
 
-pub fn upper_case_byte(byte: u8) -> u8 { 
-    if byte >= 97 { byte - 32 } else { byte }
+use crate::{context::CodeFile, directive::DirectiveValue, opcode::{CpuType, CPU_NAMES}, parser::MacroCall};
+
+const MAX_PRINTED_MACRO_CALLS: usize = 8;
+
+/* 'NAME' or 'NAME=VALUE' definition, value could be decimal, hex ($) or binary (%) number, default value is 1 */
+pub fn parse_define(text: &str) -> Result<(String, DirectiveValue), String> {
+    let (name, value) = match text.split_once('=') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => (text.trim(), "1")
+    };
+
+    let mut chars = name.chars();
+    let valid_name = matches!(chars.next(), Some(first) if first.is_ascii_alphabetic() || first == '_') && chars.all(|item| item.is_ascii_alphanumeric() || item == '_');
+    if !valid_name {
+        return Err(format!("'{}' is not a valid name", name));
+    }
+
+    match parse_number(value)? {
+        number if number <= 0xff => Ok((name.to_owned(), DirectiveValue::Byte(number as u8))),
+        number => Ok((name.to_owned(), DirectiveValue::Word(number)))
+    }
 }
 
-pub fn print_error<T: Debug>(data: &'_ [u8], error: &T, line: usize, column: usize, end: usize) {
-    let mut line_index = 0;
-    let mut start_index = 0;
-    let mut end_index = data.len()-1;
-    let mut line_found = false;
-
-    for (index, byte) in data.iter().enumerate() {
-        if *byte == b'\n' {
-            line_index += 1;
-
-            if line_index == line {
-                start_index = index+1;
-                line_found = true;
-                continue;
-            }
-
-            if line_found {
-                end_index = index;
-                break;
-            }
-        }
+/* Decimal, hex ($) or binary (%) number */
+pub fn parse_number(text: &str) -> Result<u16, String> {
+    let number = match text.as_bytes().first() {
+        Some(b'$') => u16::from_str_radix(&text[1..], 16),
+        Some(b'%') => u16::from_str_radix(&text[1..], 2),
+        _ => text.parse::<u16>()
+    };
+
+    number.map_err(|_| format!("'{}' is not a valid number", text))
+}
+
+/* 24 bit address, same format with the numbers */
+pub fn parse_address(text: &str) -> Result<u32, String> {
+    let number = match text.as_bytes().first() {
+        Some(b'$') => u32::from_str_radix(&text[1..], 16),
+        Some(b'%') => u32::from_str_radix(&text[1..], 2),
+        _ => text.parse::<u32>()
+    };
+
+    match number {
+        Ok(number) if number <= 0xffffff => Ok(number),
+        _ => Err(format!("'{}' is not a valid address", text))
+    }
+}
+
+/* 'START-END' address range, end address is included */
+pub fn parse_range(text: &str) -> Result<RangeInclusive<u32>, String> {
+    let (start, end) = text.split_once('-').ok_or_else(|| format!("'{}' is not a valid range, 'START-END' expected", text))?;
+    let start = parse_address(start.trim())?;
+    let end = parse_address(end.trim())?;
+
+    match start <= end {
+        true => Ok(start..=end),
+        false => Err(format!("'{}' is not a valid range, start is bigger than end", text))
     }
+}
+
+pub fn parse_cpu(text: &str) -> Result<CpuType, String> {
+    CpuType::from_name(text).ok_or_else(|| {
+        let names = CPU_NAMES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ");
+        format!("'{}' is not a supported CPU, available CPUs: {}", text, names)
+    })
+}
+
+pub fn print_error<T: Debug>(data: &'_ [u8], error: &T, line: usize, column: usize, end: usize) {
+    println!();
+    error!("{:?}", &error);
+    print_line(data, line, column, end);
+    println!();
+}
 
+/* Error in a code file, included files are printed with the include locations */
+pub fn print_code_error<T: Debug>(code_files: &[CodeFile], file_id: usize, error: &T, line: usize, column: usize, end: usize) {
     println!();
     error!("{:?}", &error);
+    print_line(&code_files[file_id].data, line, column, end);
+    print_include_chain(code_files, file_id);
+    println!();
+}
+
+fn print_include_chain(code_files: &[CodeFile], file_id: usize) {
+    let mut file = &code_files[file_id];
+    if file.included_from.is_some() {
+        error!("File: {}", file.path.display());
+    }
+
+    while let Some((parent_id, line)) = file.included_from {
+        file = &code_files[parent_id];
+        error!("Included from {}:{}", file.path.display(), line + 1);
+    }
+}
+
+pub fn print_macro_calls(code_files: &[CodeFile], macro_call: &Option<Rc<MacroCall>>) {
+    let mut macro_call = macro_call.as_ref();
+    let mut total_calls = 0;
+
+    while let Some(call) = macro_call {
+        /* Recursive calls could be very long */
+        if total_calls == MAX_PRINTED_MACRO_CALLS {
+            error!("... {} more macro call(s)", call.depth());
+            break;
+        }
+
+        error!("In '{}' macro", call.name);
+        print_line(&code_files[call.file_id].data, call.line, call.column, call.end);
+        println!();
+        macro_call = call.parent.as_ref();
+        total_calls += 1;
+    }
+}
+
+/* Text of the line without the line ending */
+pub fn line_text(data: &'_ [u8], line: usize) -> String {
+    let text = data.split(|byte| *byte == b'\n').nth(line).unwrap_or_default();
+    String::from_utf8_lossy(text.strip_suffix(b"\r").unwrap_or(text)).into_owned()
+}
+
+fn print_line(data: &'_ [u8], line: usize, column: usize, end: usize) {
     error!("Line: {}, column: {}", line + 1, column);
-    error!("{}", str::from_utf8(&data[start_index..end_index]).unwrap());
+    error!("{}", line_text(data, line));
     error!("{}{}", (0..column).map(|_| " ").collect::<String>(), (0..end-column).map(|_| "^").collect::<String>());
-    println!();
 }
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{code_gen::CodeGenerator, context::Context, emulator::{Emulator, StopReason, FLAG_CARRY, FLAG_DECIMAL, FLAG_INTERRUPT, FLAG_NEGATIVE, FLAG_OVERFLOW, FLAG_ZERO}, tool::parse_number};
+
+/* Test spec lines in the assembly files are written in comments with this prefix */
+pub const SPEC_PREFIX: &str = ";!";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TestSpecError {
+    #[error("{0}: {1}")]
+    Syntax(String, String),
+    #[error("{0}: '{1}' is not a number, branch or const")]
+    UnknownName(String, String),
+    #[error("{0}: 'end' expected for '{1}' test")]
+    EndExpected(String, String),
+    #[error("No test found")]
+    NoTest
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Register {
+    A,
+    X,
+    Y,
+    S,
+    P,
+    Flag(u8, &'static str)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Step {
+    SetRegister(Register, u8),
+    SetMemory(u16, Vec<u8>),
+    Call(String, u16),
+    ExpectRegister(Register, u8),
+    ExpectMemory(u16, Vec<u8>),
+    ExpectCycles(u64, bool)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct UnitTest {
+    pub name: String,
+    steps: Vec<Step>
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TestResult {
+    pub name: String,
+    pub cycles: u64,
+    pub failures: Vec<String>
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/* Values could be numbers, branch names or consts. '<' and '>' select the low and high byte */
+struct Resolver<'a> {
+    generator: &'a CodeGenerator,
+    constants: HashMap<String, i64>
+}
+
+impl<'a> Resolver<'a> {
+    fn value(&self, location: &str, text: &str) -> Result<i64, TestSpecError> {
+        let (text, shift) = match text.as_bytes().first() {
+            Some(b'<') => (&text[1..], Some(0)),
+            Some(b'>') => (&text[1..], Some(8)),
+            _ => (text, None)
+        };
+
+        let value = match (parse_number(text), self.generator.branches.get(text), self.constants.get(text)) {
+            (Ok(number), _, _) => number as i64,
+            (_, Some(address), _) => *address as i64,
+            (_, _, Some(value)) => *value,
+            _ => return Err(TestSpecError::UnknownName(location.to_owned(), text.to_owned()))
+        };
+
+        Ok(match shift {
+            Some(shift) => (value >> shift) & 0xff,
+            None => value
+        })
+    }
+
+    fn byte(&self, location: &str, text: &str) -> Result<u8, TestSpecError> {
+        match self.value(location, text)? {
+            value @ 0..=0xff => Ok(value as u8),
+            _ => Err(TestSpecError::Syntax(location.to_owned(), format!("'{}' is bigger than a byte", text)))
+        }
+    }
+
+    fn address(&self, location: &str, text: &str) -> Result<u16, TestSpecError> {
+        let value = match text.split_once('+') {
+            Some((base, offset)) => self.value(location, base.trim())? + self.value(location, offset.trim())?,
+            None => self.value(location, text)?
+        };
+
+        match value {
+            0..=0xffff => Ok(value as u16),
+            _ => Err(TestSpecError::Syntax(location.to_owned(), format!("'{}' is not a valid address", text)))
+        }
+    }
+}
+
+fn parse_register(name: &str) -> Option<Register> {
+    Some(match name.to_ascii_uppercase().as_str() {
+        "A" => Register::A,
+        "X" => Register::X,
+        "Y" => Register::Y,
+        "S" => Register::S,
+        "P" => Register::P,
+        "C" => Register::Flag(FLAG_CARRY, "C"),
+        "Z" => Register::Flag(FLAG_ZERO, "Z"),
+        "I" => Register::Flag(FLAG_INTERRUPT, "I"),
+        "D" => Register::Flag(FLAG_DECIMAL, "D"),
+        "V" => Register::Flag(FLAG_OVERFLOW, "V"),
+        "N" => Register::Flag(FLAG_NEGATIVE, "N"),
+        _ => return None
+    })
+}
+
+/* 'A=1 [table+1]=$01, $02' is splitted to the assignments */
+fn assignments(text: &str) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    for token in text.split_whitespace() {
+        match items.last_mut() {
+            Some(last) if last.ends_with(['=', ',']) || token.starts_with(['=', ',']) || token.starts_with("<=") => last.push_str(token),
+            _ => items.push(token.to_owned())
+        };
+    }
+    items
+}
+
+fn parse_assignment(resolver: &Resolver, location: &str, text: &str, expect: bool) -> Result<Step, TestSpecError> {
+    let syntax = |message: String| TestSpecError::Syntax(location.to_owned(), message);
+
+    if expect {
+        if let Some((name, value)) = text.split_once("<=") {
+            return match name.eq_ignore_ascii_case("cycles") {
+                true => Ok(Step::ExpectCycles(resolver.value(location, value)?.max(0) as u64, true)),
+                false => Err(syntax(format!("'<=' is only supported for cycles, '{}' found", name)))
+            };
+        }
+    }
+
+    let (name, value) = text.split_once('=').ok_or_else(|| syntax(format!("'NAME=VALUE' expected, '{}' found", text)))?;
+
+    if let Some(address) = name.strip_prefix('[').and_then(|name| name.strip_suffix(']')) {
+        let address = resolver.address(location, address.trim())?;
+        let bytes = value.split(',').map(|item| resolver.byte(location, item.trim())).collect::<Result<Vec<_>, _>>()?;
+        return Ok(match expect {
+            true => Step::ExpectMemory(address, bytes),
+            false => Step::SetMemory(address, bytes)
+        });
+    }
+
+    if expect && name.eq_ignore_ascii_case("cycles") {
+        return Ok(Step::ExpectCycles(resolver.value(location, value)?.max(0) as u64, false));
+    }
+
+    let register = parse_register(name).ok_or_else(|| syntax(format!("'{}' is not a register, flag or memory address", name)))?;
+    let value = resolver.byte(location, value)?;
+
+    if let Register::Flag(_, flag) = register {
+        if value > 1 {
+            return Err(syntax(format!("{} flag could be 0 or 1", flag)));
+        }
+    }
+
+    Ok(match expect {
+        true => Step::ExpectRegister(register, value),
+        false => Step::SetRegister(register, value)
+    })
+}
+
+/* Test spec lines from the assembly files, line locations are 'path:line' */
+pub fn embedded_spec(context: &Context) -> Vec<(String, String)> {
+    let mut lines = Vec::new();
+    for file in context.code_files.borrow().iter() {
+        for (index, line) in String::from_utf8_lossy(&file.data).lines().enumerate() {
+            if let Some(spec) = line.trim_start().strip_prefix(SPEC_PREFIX) {
+                lines.push((format!("{}:{}", file.path.display(), index + 1), spec.to_owned()));
+            }
+        }
+    }
+    lines
+}
+
+/* Sidecar spec file lines */
+pub fn file_spec(path: &str, data: &str) -> Vec<(String, String)> {
+    data.lines().enumerate().map(|(index, line)| (format!("{}:{}", path, index + 1), line.to_owned())).collect()
+}
+
+/*
+test NAME
+    set A=$10 C=1 [buffer]=$01, $02
+    call routine
+    expect A=$20 Z=0 [buffer+1]=$03 cycles<=40
+end
+*/
+pub fn parse_spec(lines: &[(String, String)], generator: &CodeGenerator, context: &Context) -> Result<Vec<UnitTest>, TestSpecError> {
+    let resolver = Resolver { generator, constants: generator.constants(context).into_iter().collect() };
+    let mut tests = Vec::new();
+    let mut current: Option<(String, UnitTest)> = None;
+
+    for (location, line) in lines.iter() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match (keyword.to_ascii_lowercase().as_str(), current.as_mut()) {
+            ("test", None) if !rest.is_empty() => current = Some((location.clone(), UnitTest { name: rest.trim_matches('"').to_owned(), steps: Vec::new() })),
+            ("test", None) => return Err(TestSpecError::Syntax(location.clone(), "Test name expected".to_owned())),
+            ("test", Some((test_location, test))) => return Err(TestSpecError::EndExpected(test_location.clone(), test.name.clone())),
+            ("end", Some(_)) => tests.extend(current.take().map(|(_, test)| test)),
+            ("set", Some((_, test))) | ("expect", Some((_, test))) => {
+                for item in assignments(rest) {
+                    test.steps.push(parse_assignment(&resolver, location, &item, keyword.eq_ignore_ascii_case("expect"))?);
+                }
+            },
+            ("call", Some((_, test))) => test.steps.push(Step::Call(rest.to_owned(), resolver.address(location, rest)?)),
+            (_, Some(_)) => return Err(TestSpecError::Syntax(location.clone(), format!("'set', 'call', 'expect' or 'end' expected, '{}' found", keyword))),
+            (_, None) => return Err(TestSpecError::Syntax(location.clone(), format!("'test' expected, '{}' found", keyword)))
+        };
+    }
+
+    match current {
+        Some((location, test)) => Err(TestSpecError::EndExpected(location, test.name)),
+        None => Ok(tests)
+    }
+}
+
+fn register_value(emulator: &Emulator, register: Register) -> u8 {
+    match register {
+        Register::A => emulator.a,
+        Register::X => emulator.x,
+        Register::Y => emulator.y,
+        Register::S => emulator.s,
+        Register::P => emulator.p,
+        Register::Flag(flag, _) => (emulator.p & flag != 0) as u8
+    }
+}
+
+fn register_text(register: Register, value: u8) -> (&'static str, String) {
+    match register {
+        Register::A => ("A", format!("${:02X}", value)),
+        Register::X => ("X", format!("${:02X}", value)),
+        Register::Y => ("Y", format!("${:02X}", value)),
+        Register::S => ("S", format!("${:02X}", value)),
+        Register::P => ("P", format!("${:02X}", value)),
+        Register::Flag(_, name) => (name, value.to_string())
+    }
+}
+
+fn bytes_text(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("${:02X}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+/* Every test starts with a new emulator, cycles are counted for the last call */
+pub fn run_test(test: &UnitTest, program: &[u8], start: u32, max_cycles: u64) -> TestResult {
+    let mut emulator = Emulator::new();
+    let mut result = TestResult { name: test.name.clone(), cycles: 0, failures: Vec::new() };
+
+    if let Err(error) = emulator.load(start, program) {
+        result.failures.push(error.to_string());
+        return result;
+    }
+
+    for step in test.steps.iter() {
+        match step {
+            Step::SetRegister(register, value) => match register {
+                Register::A => emulator.a = *value,
+                Register::X => emulator.x = *value,
+                Register::Y => emulator.y = *value,
+                Register::S => emulator.s = *value,
+                Register::P => emulator.p = *value,
+                Register::Flag(flag, _) => match *value != 0 {
+                    true => emulator.p |= flag,
+                    false => emulator.p &= !flag
+                }
+            },
+            Step::SetMemory(address, bytes) => {
+                for (index, byte) in bytes.iter().enumerate() {
+                    emulator.memory[address.wrapping_add(index as u16) as usize] = *byte;
+                }
+            },
+            Step::Call(name, address) => {
+                emulator.cycles = 0;
+                let stop = match emulator.call(*address, max_cycles) {
+                    Ok(StopReason::Return) => None,
+                    Ok(StopReason::Break) => Some(format!("{} stopped with BRK at ${:04X}", name, emulator.pc)),
+                    Ok(StopReason::Loop) => Some(format!("{} stopped with endless loop at ${:04X}", name, emulator.pc)),
+                    Err(error) => Some(format!("{}: {}", name, error))
+                };
+
+                result.cycles = emulator.cycles;
+                if let Some(stop) = stop {
+                    result.failures.push(stop);
+                    return result;
+                }
+            },
+            Step::ExpectRegister(register, expected) => {
+                let found = register_value(&emulator, *register);
+                if found != *expected {
+                    let (name, expected) = register_text(*register, *expected);
+                    result.failures.push(format!("{}: expected {}, found {}", name, expected, register_text(*register, found).1));
+                }
+            },
+            Step::ExpectMemory(address, expected) => {
+                let found: Vec<u8> = (0..expected.len()).map(|index| emulator.memory[address.wrapping_add(index as u16) as usize]).collect();
+                if &found != expected {
+                    result.failures.push(format!("[${:04X}]: expected {}, found {}", address, bytes_text(expected), bytes_text(&found)));
+                }
+            },
+            Step::ExpectCycles(expected, at_most) => {
+                match (*at_most, emulator.cycles) {
+                    (true, found) if found > *expected => result.failures.push(format!("Cycles: expected at most {}, found {}", expected, found)),
+                    (false, found) if found != *expected => result.failures.push(format!("Cycles: expected {}, found {}", expected, found)),
+                    _ => ()
+                };
+            }
+        };
+    }
+
+    result
+}